@@ -1,7 +1,40 @@
 use clap::ArgMatches;
+use image::{ImageFormat, Rgba};
+use std::fs;
+use std::path::Path;
 use std::process::exit;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use crate::unicode_art::animation::FrameSelection;
+use crate::unicode_art::aspect_ratio::{SimpleAspectRatio, TermFit};
 use crate::unicode_art::braille::DEFAULT_THRESHOLD;
+use crate::unicode_art::color::parse_color;
+use crate::unicode_art::colorize::{Colorizer, Direction};
+use crate::unicode_art::error::UnicodeArtError;
+use crate::unicode_art::export::{Metadata, OutputFormat};
+use crate::unicode_art::figlet::{FigFont, Justify, Layout, DEFAULT_FONT, FONT_NAMES};
+use crate::unicode_art::fractal::{
+    CHARSET_ASCII, CHARSET_BLOCKS, CHARSET_DENSITY, DEFAULT_ITERATIONS, DEFAULT_JULIA_C,
+    DEFAULT_ZOOM,
+};
+use crate::unicode_art::gradient::Gradient;
+use crate::unicode_art::input::DEFAULT_MEMORY_LIMIT;
+use crate::unicode_art::preprocess::Preprocess;
+use crate::unicode_art::procedural::{DEFAULT_GENERATIONS, DEFAULT_RULE, DEFAULT_SCALE};
+use crate::unicode_art::reverse::ReverseMode;
+use crate::unicode_art::stream::DEFAULT_FRAME_RATE;
+use crate::unicode_art::transform::Transform;
+use crate::unicode_art::ttf::DEFAULT_PIXEL_SIZE;
+
+fn parse_or_exit<T: FromStr>(matches: &ArgMatches, name: &str) -> Option<T> {
+    matches.value_of(name).map(|val| {
+        val.parse::<T>().unwrap_or_else(|_| {
+            eprintln!("Invalid {}: {}", name.to_lowercase(), val);
+            exit(exitcode::USAGE)
+        })
+    })
+}
 
 pub trait NumColumns {
     fn num_cols(&self, min_cols: u32, default_cols: u32) -> u32;
@@ -31,3 +64,449 @@ impl BrailleThreshold for ArgMatches {
         })
     }
 }
+
+pub trait BackgroundColor {
+    fn background(&self) -> Option<Rgba<u8>>;
+}
+
+impl BackgroundColor for ArgMatches {
+    fn background(&self) -> Option<Rgba<u8>> {
+        self.value_of("BACKGROUND").map(|val| {
+            parse_color(val).unwrap_or_else(|| {
+                eprintln!("Invalid background color: {}", val);
+                exit(exitcode::USAGE)
+            })
+        })
+    }
+}
+
+pub trait Preprocessing {
+    fn preprocess(&self) -> Preprocess;
+}
+
+impl Preprocessing for ArgMatches {
+    fn preprocess(&self) -> Preprocess {
+        Preprocess {
+            auto_levels: self.is_present("AUTO_LEVELS"),
+            equalize: self.is_present("EQUALIZE"),
+            brightness: parse_or_exit(self, "BRIGHTNESS"),
+            contrast: parse_or_exit(self, "CONTRAST"),
+            gamma: parse_or_exit::<f32>(self, "GAMMA").inspect(|&gamma| {
+                if gamma <= 0.0 {
+                    eprintln!("Invalid gamma. gamma must be positive");
+                    exit(exitcode::USAGE)
+                }
+            }),
+            blur: parse_or_exit(self, "BLUR"),
+            sharpen: parse_or_exit(self, "SHARPEN"),
+        }
+    }
+}
+
+pub trait GeometricTransform {
+    fn transform(&self) -> Transform;
+}
+
+impl GeometricTransform for ArgMatches {
+    fn transform(&self) -> Transform {
+        Transform {
+            orientation: None,
+            crop: parse_or_exit(self, "CROP"),
+            rotate: parse_or_exit(self, "ROTATE"),
+            flip: parse_or_exit(self, "FLIP"),
+        }
+    }
+}
+
+pub trait Dimensions {
+    fn aspect_ratio(&self, num_cols: u32) -> SimpleAspectRatio;
+    /// Size from `--width`, `--height` and `--cell-aspect`, with the given
+    /// terminal fit instead of `--fit`.
+    fn aspect_ratio_with(&self, num_cols: u32, termfit: TermFit) -> SimpleAspectRatio;
+    /// `--fit` for commands that always print to the terminal.
+    fn termfit(&self) -> TermFit;
+}
+
+impl Dimensions for ArgMatches {
+    fn aspect_ratio(&self, num_cols: u32) -> SimpleAspectRatio {
+        // files don't care about the terminal
+        let is_file = self.is_present("OUTPUT_DIR") || self.is_present("OUTPUT");
+        let termfit = match is_file && self.occurrences_of("FIT") == 0 {
+            true => TermFit::None,
+            false => self.termfit(),
+        };
+        self.aspect_ratio_with(num_cols, termfit)
+    }
+
+    fn aspect_ratio_with(&self, num_cols: u32, termfit: TermFit) -> SimpleAspectRatio {
+        let num_rows = parse_or_exit::<u32>(self, "NUM_ROWS");
+        let is_cols_given = self.occurrences_of("NUM_COLS") > 0;
+        let aspect_ratio = match num_rows {
+            Some(0) => {
+                eprintln!("Invalid num_rows. minimum rows: 1");
+                exit(exitcode::USAGE)
+            }
+            Some(rows) if is_cols_given => {
+                SimpleAspectRatio::new(Some(num_cols), Some(rows), termfit, false)
+            }
+            Some(rows) => SimpleAspectRatio::new_auto_width(rows, termfit, false),
+            None => SimpleAspectRatio::new_auto_height(num_cols, termfit, false),
+        };
+        match parse_or_exit::<f64>(self, "CELL_ASPECT") {
+            Some(cell_aspect) if cell_aspect <= 0.0 || !cell_aspect.is_finite() => {
+                eprintln!("Invalid cell aspect. cell aspect must be positive");
+                exit(exitcode::USAGE)
+            }
+            Some(cell_aspect) => aspect_ratio.with_cell_aspect(cell_aspect),
+            None => aspect_ratio,
+        }
+    }
+
+    fn termfit(&self) -> TermFit {
+        parse_or_exit(self, "FIT").unwrap_or_default()
+    }
+}
+
+pub trait Animation {
+    fn frame_selection(&self) -> Option<FrameSelection>;
+    /// `None` leaves it to the animation.
+    fn loop_count(&self) -> Option<u32>;
+    /// Length of a pattern animation, zero for a still pattern.
+    fn duration(&self) -> Duration;
+}
+
+impl Animation for ArgMatches {
+    fn frame_selection(&self) -> Option<FrameSelection> {
+        parse_or_exit(self, "FRAMES")
+    }
+
+    fn loop_count(&self) -> Option<u32> {
+        parse_or_exit(self, "LOOP")
+    }
+
+    fn duration(&self) -> Duration {
+        match parse_or_exit::<f64>(self, "DURATION") {
+            Some(seconds) if seconds < 0.0 || !seconds.is_finite() => {
+                eprintln!("Invalid duration. duration must not be negative");
+                exit(exitcode::USAGE)
+            }
+            Some(seconds) => Duration::from_secs_f64(seconds),
+            None => Duration::ZERO,
+        }
+    }
+}
+
+pub trait VideoStream {
+    fn raw_size(&self) -> Option<(u32, u32)>;
+    fn frame_rate(&self) -> f64;
+}
+
+impl VideoStream for ArgMatches {
+    fn raw_size(&self) -> Option<(u32, u32)> {
+        self.value_of("RAW").map(|val| {
+            let size = val
+                .split_once('x')
+                .and_then(|(w, h)| Some((w.parse::<u32>().ok()?, h.parse::<u32>().ok()?)));
+            match size {
+                Some((width, height)) if width > 0 && height > 0 => (width, height),
+                _ => {
+                    eprintln!("Invalid raw frame size: {}", val);
+                    exit(exitcode::USAGE)
+                }
+            }
+        })
+    }
+
+    fn frame_rate(&self) -> f64 {
+        match parse_or_exit::<f64>(self, "FPS") {
+            Some(fps) if fps <= 0.0 || !fps.is_finite() => {
+                eprintln!("Invalid fps. fps must be positive");
+                exit(exitcode::USAGE)
+            }
+            Some(fps) => fps,
+            None => DEFAULT_FRAME_RATE,
+        }
+    }
+}
+
+pub trait StdinLimit {
+    /// In bytes
+    fn stdin_limit(&self) -> u64;
+}
+
+impl StdinLimit for ArgMatches {
+    fn stdin_limit(&self) -> u64 {
+        parse_or_exit::<u64>(self, "STDIN_LIMIT")
+            .map_or(DEFAULT_MEMORY_LIMIT, |mib| mib.saturating_mul(1024 * 1024))
+    }
+}
+
+pub trait Parallelism {
+    fn jobs(&self) -> usize;
+}
+
+impl Parallelism for ArgMatches {
+    fn jobs(&self) -> usize {
+        match parse_or_exit::<usize>(self, "JOBS") {
+            Some(0) => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            Some(jobs) => jobs,
+            None => 1,
+        }
+    }
+}
+
+pub trait OutputOptions {
+    /// `--format`, else the extension of `--output`, else `default`.
+    fn output_format(&self, default: OutputFormat) -> OutputFormat;
+}
+
+impl OutputOptions for ArgMatches {
+    fn output_format(&self, default: OutputFormat) -> OutputFormat {
+        let format =
+            parse_or_exit(self, "FORMAT").unwrap_or_else(|| match self.value_of("OUTPUT") {
+                Some(output) => OutputFormat::from_path(Path::new(output)).unwrap_or_else(|| {
+                    eprintln!("Unknown output format: {}, pass --format", output);
+                    exit(exitcode::USAGE)
+                }),
+                None => default,
+            });
+        match format {
+            OutputFormat::Text if self.is_present("KEEP_ESCAPES") => OutputFormat::Ansi,
+            format => format,
+        }
+    }
+}
+
+pub trait ExportMetadata {
+    fn metadata(&self) -> Metadata;
+}
+
+impl ExportMetadata for ArgMatches {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            title: self.value_of("TITLE").map(str::to_string),
+            author: self.value_of("AUTHOR").map(str::to_string),
+            group: self.value_of("GROUP").map(str::to_string),
+            font: self.value_of("SAUCE_FONT").map(str::to_string),
+        }
+    }
+}
+
+pub trait ReverseOptions {
+    fn reverse_mode(&self) -> ReverseMode;
+    /// Image format from the extension of `--output`.
+    fn image_format(&self) -> ImageFormat;
+}
+
+impl ReverseOptions for ArgMatches {
+    fn reverse_mode(&self) -> ReverseMode {
+        parse_or_exit(self, "MODE").unwrap_or(ReverseMode::Auto)
+    }
+
+    fn image_format(&self) -> ImageFormat {
+        let output = self.value_of("OUTPUT").unwrap_or_default();
+        ImageFormat::from_path(output).unwrap_or_else(|_| {
+            eprintln!("Unknown image format: {}", output);
+            exit(exitcode::USAGE)
+        })
+    }
+}
+
+/// A complex number written as `RE,IM`.
+fn parse_complex(matches: &ArgMatches, name: &str) -> Option<(f64, f64)> {
+    matches.value_of(name).map(|val| {
+        let complex = val
+            .split_once(',')
+            .and_then(|(re, im)| Some((re.trim().parse().ok()?, im.trim().parse().ok()?)));
+        match complex {
+            Some((re, im)) if f64::is_finite(re) && f64::is_finite(im) => (re, im),
+            _ => {
+                eprintln!(
+                    "Invalid {}: {}, expected RE,IM e.g. -0.75,0.1",
+                    name.to_lowercase(),
+                    val
+                );
+                exit(exitcode::USAGE)
+            }
+        }
+    })
+}
+
+pub trait FractalView {
+    fn center(&self) -> Option<(f64, f64)>;
+    /// The constant of a Julia set.
+    fn julia_c(&self) -> (f64, f64);
+    fn zoom(&self) -> f64;
+    fn iterations(&self) -> u32;
+}
+
+impl FractalView for ArgMatches {
+    fn center(&self) -> Option<(f64, f64)> {
+        parse_complex(self, "CENTER")
+    }
+
+    fn julia_c(&self) -> (f64, f64) {
+        parse_complex(self, "C").unwrap_or(DEFAULT_JULIA_C)
+    }
+
+    fn zoom(&self) -> f64 {
+        match parse_or_exit::<f64>(self, "ZOOM") {
+            Some(zoom) if zoom <= 0.0 || !zoom.is_finite() => {
+                eprintln!("Invalid zoom. zoom must be positive");
+                exit(exitcode::USAGE)
+            }
+            Some(zoom) => zoom,
+            None => DEFAULT_ZOOM,
+        }
+    }
+
+    fn iterations(&self) -> u32 {
+        match parse_or_exit::<u32>(self, "ITERATIONS") {
+            Some(0) => {
+                eprintln!("Invalid iterations. minimum iterations: 1");
+                exit(exitcode::USAGE)
+            }
+            Some(iterations) => iterations,
+            None => DEFAULT_ITERATIONS,
+        }
+    }
+}
+
+pub trait PatternStyle {
+    /// A named charset or the characters themselves.
+    fn charset(&self) -> Option<&str>;
+    /// `--gradient`, or viridis for `--color`.
+    fn gradient(&self) -> Option<Gradient>;
+}
+
+impl PatternStyle for ArgMatches {
+    fn charset(&self) -> Option<&str> {
+        let charset = match self.value_of("CHARSET")? {
+            "ascii" => CHARSET_ASCII,
+            "density" => CHARSET_DENSITY,
+            "blocks" => CHARSET_BLOCKS,
+            charset => charset,
+        };
+        if charset.chars().count() < 2 {
+            eprintln!("Invalid charset. a charset needs at least 2 characters");
+            exit(exitcode::USAGE)
+        }
+        Some(charset)
+    }
+
+    fn gradient(&self) -> Option<Gradient> {
+        match parse_or_exit(self, "GRADIENT") {
+            Some(gradient) => Some(gradient),
+            None if self.is_present("COLOR") => Some(Gradient::viridis()),
+            None => None,
+        }
+    }
+}
+
+pub trait ProceduralPattern {
+    /// `--seed`, or a new one every run.
+    fn seed(&self) -> u64;
+    fn scale(&self) -> f64;
+    fn generations(&self) -> u32;
+    fn rule(&self) -> u8;
+}
+
+impl ProceduralPattern for ArgMatches {
+    fn seed(&self) -> u64 {
+        parse_or_exit(self, "SEED").unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+        })
+    }
+
+    fn scale(&self) -> f64 {
+        match parse_or_exit::<f64>(self, "SCALE") {
+            Some(scale) if scale <= 0.0 || !scale.is_finite() => {
+                eprintln!("Invalid scale. scale must be positive");
+                exit(exitcode::USAGE)
+            }
+            Some(scale) => scale,
+            None => DEFAULT_SCALE,
+        }
+    }
+
+    fn generations(&self) -> u32 {
+        parse_or_exit(self, "GENERATIONS").unwrap_or(DEFAULT_GENERATIONS)
+    }
+
+    fn rule(&self) -> u8 {
+        parse_or_exit(self, "RULE").unwrap_or(DEFAULT_RULE)
+    }
+}
+
+pub trait TextBanner {
+    /// A built in font, or a `.flf` file.
+    fn font(&self) -> FigFont;
+    /// Content of a `.ttf` or `.otf` `--font`, `None` for FIGlet fonts.
+    fn outline_font(&self) -> Option<Vec<u8>>;
+    /// `--size` of outline fonts in pixels.
+    fn pixel_size(&self) -> f32;
+    fn layout(&self) -> Option<Layout>;
+    fn justify(&self) -> Justify;
+}
+
+impl TextBanner for ArgMatches {
+    fn font(&self) -> FigFont {
+        let font = match self.value_of("FONT").unwrap_or(DEFAULT_FONT) {
+            name if FONT_NAMES.contains(&name) => FigFont::named(name),
+            path => fs::read_to_string(path)
+                .map_err(UnicodeArtError::from)
+                .and_then(|data| FigFont::parse(&data)),
+        };
+        font.unwrap_or_else(|err| {
+            eprintln!("Invalid font: {}", err);
+            exit(exitcode::USAGE)
+        })
+    }
+
+    fn outline_font(&self) -> Option<Vec<u8>> {
+        let path = Path::new(self.value_of("FONT")?);
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        if extension != "ttf" && extension != "otf" {
+            return None;
+        }
+        let data = fs::read(path).unwrap_or_else(|err| {
+            eprintln!("Invalid font: {}", err);
+            exit(exitcode::USAGE)
+        });
+        Some(data)
+    }
+
+    fn pixel_size(&self) -> f32 {
+        match parse_or_exit::<f32>(self, "SIZE") {
+            Some(size) if size < 1.0 || !size.is_finite() => {
+                eprintln!("Invalid size. size must be at least 1 pixel");
+                exit(exitcode::USAGE)
+            }
+            Some(size) => size,
+            None => DEFAULT_PIXEL_SIZE,
+        }
+    }
+
+    fn layout(&self) -> Option<Layout> {
+        parse_or_exit(self, "LAYOUT")
+    }
+
+    fn justify(&self) -> Justify {
+        parse_or_exit(self, "JUSTIFY").unwrap_or(Justify::Left)
+    }
+}
+
+pub trait Colorize {
+    /// `--colorize` in the `--colorize-direction`.
+    fn colorizer(&self) -> Option<Colorizer>;
+}
+
+impl Colorize for ArgMatches {
+    fn colorizer(&self) -> Option<Colorizer> {
+        let tint = parse_or_exit(self, "COLORIZE")?;
+        let direction = parse_or_exit(self, "COLORIZE_DIRECTION").unwrap_or(Direction::Horizontal);
+        Some(Colorizer::new(tint, direction))
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use image::ImageFormat;
+
+use crate::unicode_art::error::UnicodeArtError;
+
+pub const DEFAULT_NAME_TEMPLATE: &str = "{stem}.{format}";
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Image files directly inside a directory, in name order.
+fn images_in(dir: &Path) -> Result<Vec<PathBuf>, UnicodeArtError> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.retain(|path| path.is_file() && ImageFormat::from_path(path).is_ok());
+    paths.sort();
+    Ok(paths)
+}
+
+fn no_images(arg: &str) -> UnicodeArtError {
+    UnicodeArtError::InvalidArgumentError(format!("no images found in {}", arg))
+}
+
+/// Expand directories and glob patterns (for shells that leave them quoted)
+/// into image paths. Plain paths are kept as they are, so that missing files
+/// show up as failures.
+pub fn expand_inputs<'a, I>(args: I) -> Result<Vec<PathBuf>, UnicodeArtError>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut paths = Vec::new();
+    for arg in args {
+        if is_glob(arg) {
+            let matches = glob::glob(arg)
+                .map_err(|err| UnicodeArtError::InvalidArgumentError(err.to_string()))?
+                .filter_map(Result::ok)
+                .filter(|path| path.is_file());
+            let count = paths.len();
+            paths.extend(matches);
+            if paths.len() == count {
+                return Err(no_images(arg));
+            }
+        } else if Path::new(arg).is_dir() {
+            let images = images_in(Path::new(arg))?;
+            if images.is_empty() {
+                return Err(no_images(arg));
+            }
+            paths.extend(images);
+        } else {
+            paths.push(PathBuf::from(arg));
+        }
+    }
+    Ok(paths)
+}
+
+/// Fill in `{stem}`, `{ext}`, `{parent}` and `{format}` of a file name
+/// template.
+pub fn output_name(template: &str, path: &Path, format: &str) -> String {
+    let stem = path
+        .file_stem()
+        .map_or("output".into(), |stem| stem.to_string_lossy());
+    let ext = path
+        .extension()
+        .map_or("".into(), |ext| ext.to_string_lossy());
+    let parent = path
+        .parent()
+        .and_then(Path::file_name)
+        .map_or("".into(), |parent| parent.to_string_lossy());
+    template
+        .replace("{stem}", &stem)
+        .replace("{ext}", &ext)
+        .replace("{parent}", &parent)
+        .replace("{format}", format)
+}
+
+/// The output name of every input, refusing names that two inputs share as
+/// one would overwrite the other.
+pub fn output_names(
+    template: &str,
+    inputs: &[PathBuf],
+    format: &str,
+) -> Result<Vec<String>, UnicodeArtError> {
+    let mut seen: HashMap<String, &Path> = HashMap::new();
+    let mut names = Vec::with_capacity(inputs.len());
+    for path in inputs {
+        let name = output_name(template, path, format);
+        if let Some(other) = seen.insert(name.clone(), path) {
+            return Err(UnicodeArtError::InvalidArgumentError(format!(
+                "{} and {} would both be written to {}, add {{parent}} or {{ext}} to --name",
+                other.display(),
+                path.display(),
+                name
+            )));
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+/// Render every input on up to `jobs` threads. A failing input doesn't stop
+/// the others; the failures are returned in input order.
+pub fn run_batch<F>(inputs: &[PathBuf], jobs: usize, render: F) -> Vec<(PathBuf, UnicodeArtError)>
+where
+    F: Fn(&Path) -> Result<(), UnicodeArtError> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let failures = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, inputs.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = inputs.get(index) else {
+                    break;
+                };
+                if let Err(err) = render(path) {
+                    failures.lock().unwrap().push((index, err));
+                }
+            });
+        }
+    });
+    let mut failures = failures.into_inner().unwrap();
+    failures.sort_by_key(|(index, _)| *index);
+    failures
+        .into_iter()
+        .map(|(index, err)| (inputs[index].clone(), err))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_name() {
+        let path = Path::new("thumbs/shoe.large.png");
+        assert_eq!(
+            "shoe.large.txt",
+            output_name(DEFAULT_NAME_TEMPLATE, path, "txt")
+        );
+        assert_eq!("art-shoe.large", output_name("art-{stem}", path, "txt"));
+        assert_eq!(
+            "thumbs-shoe.large.png.txt",
+            output_name("{parent}-{stem}.{ext}.{format}", path, "txt")
+        );
+    }
+
+    #[test]
+    fn test_output_names() {
+        let inputs = [
+            PathBuf::from("x/a.png"),
+            PathBuf::from("y/a.png"),
+            PathBuf::from("x/a.jpg"),
+        ];
+        assert!(output_names(DEFAULT_NAME_TEMPLATE, &inputs[..2], "txt").is_err());
+        assert!(output_names(DEFAULT_NAME_TEMPLATE, &inputs[1..], "txt").is_err());
+        assert_eq!(
+            vec!["x-a.png.txt", "y-a.png.txt", "x-a.jpg.txt"],
+            output_names("{parent}-{stem}.{ext}.{format}", &inputs, "txt").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_expand_inputs() -> Result<(), UnicodeArtError> {
+        let dir = tempfile::tempdir()?;
+        for name in ["b.png", "a.jpg", "notes.txt"] {
+            fs::write(dir.path().join(name), b"")?;
+        }
+        let dir_path = dir.path().to_str().unwrap();
+        let names = |paths: Vec<PathBuf>| -> Vec<String> {
+            paths
+                .iter()
+                .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+        assert_eq!(vec!["a.jpg", "b.png"], names(expand_inputs([dir_path])?));
+        let pattern = format!("{}/*.png", dir_path);
+        assert_eq!(
+            vec!["b.png", "missing.png"],
+            names(expand_inputs([pattern.as_str(), "missing.png"])?)
+        );
+        let pattern = format!("{}/*.gif", dir_path);
+        assert!(expand_inputs([pattern.as_str()]).is_err());
+        let empty = tempfile::tempdir()?;
+        assert!(expand_inputs([empty.path().to_str().unwrap()]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_batch() {
+        let inputs: Vec<_> = (0..20).map(|i| PathBuf::from(i.to_string())).collect();
+        let rendered = AtomicUsize::new(0);
+        let failures = run_batch(&inputs, 4, |path| {
+            rendered.fetch_add(1, Ordering::Relaxed);
+            match path.to_str().unwrap().parse::<u32>().unwrap() % 7 {
+                0 => Err(UnicodeArtError::UnsupportError),
+                _ => Ok(()),
+            }
+        });
+        assert_eq!(20, rendered.into_inner());
+        let failed: Vec<_> = failures.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(
+            vec![inputs[0].clone(), inputs[7].clone(), inputs[14].clone()],
+            failed
+        );
+    }
+}
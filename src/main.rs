@@ -1,42 +1,93 @@
 extern crate exitcode;
 
 mod arg;
+mod batch;
 mod unicode_art;
 
-use crate::arg::{BrailleThreshold, NumColumns};
+use crate::arg::{
+    Animation, BackgroundColor, BrailleThreshold, Colorize, Dimensions, ExportMetadata,
+    FractalView, GeometricTransform, NumColumns, OutputOptions, Parallelism, PatternStyle,
+    Preprocessing, ProceduralPattern, ReverseOptions, StdinLimit, TextBanner, VideoStream,
+};
+use crate::batch::{expand_inputs, output_names, run_batch, DEFAULT_NAME_TEMPLATE};
+use crate::unicode_art::alpha::Composite;
+use crate::unicode_art::animation::{
+    animate, decode_frames, render_frames, AnimatedOption, Frame, Player,
+};
+use crate::unicode_art::ansiart::DEFAULT_SAUCE_FONT;
+use crate::unicode_art::aspect_ratio::{SimpleAspectRatio, TermFit, DEFAULT_CELL_ASPECT};
 use crate::unicode_art::block::BlockUnicodeArtOption;
 use crate::unicode_art::braille::BrailleAsciiArtOption;
 use crate::unicode_art::braille::DEFAULT_THRESHOLD;
 use crate::unicode_art::classic::ClassicAsciiArtOption;
+use crate::unicode_art::colorize::ColorizedOption;
+use crate::unicode_art::emoji::EmojiUnicodeArtOption;
 use crate::unicode_art::error::UnicodeArtError;
-use crate::unicode_art::input::Input;
-use crate::unicode_art::mandel::MandelAsciiArtOption;
+use crate::unicode_art::export::{write_atomic, OutputFormat};
+use crate::unicode_art::figlet::{FigletAsciiArtOption, FONT_NAMES};
+use crate::unicode_art::fractal::{Fractal, FractalAsciiArtOption};
+use crate::unicode_art::input::{Input, DEFAULT_MEMORY_LIMIT};
+use crate::unicode_art::metrics::compare;
+use crate::unicode_art::procedural::{Procedural, ProceduralAsciiArtOption};
+use crate::unicode_art::reverse::ansi_to_image;
+use crate::unicode_art::stream::{
+    render_stream, FrameStream, RawRgbStream, StreamPlayer, Y4mStream, DEFAULT_FRAME_RATE,
+};
 use crate::unicode_art::subpixel::SubpixelUnicodeArtOption;
-use crate::unicode_art::UnicodeArtOption;
+use crate::unicode_art::term::is_terminal;
+use crate::unicode_art::transform::{exif_orientation, Transform};
+use crate::unicode_art::ttf::TtfText;
+use crate::unicode_art::{PatternOption, UnicodeArtOption};
 
-use std::io::{stdin, stdout, BufWriter};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{
+    self, stdin, stdout, BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write,
+};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::time::Duration;
 
 use clap::lazy_static::lazy_static;
-use clap::{Arg, Command};
-use image::io::Reader;
-use image::{DynamicImage, RgbImage};
+use clap::{Arg, ArgMatches, Command};
+use image::DynamicImage;
 
 const MIN_NUM_COLS: u32 = 1;
-const ARG_PRESET: &'static str = "PRESET";
-const SUB_COMMAND_CLASSIC: &'static str = "classic";
-const SUB_COMMAND_BRAILLE: &'static str = "braille";
-const SUB_COMMAND_SUBPIXEL: &'static str = "subpixel";
-const SUB_COMMAND_PATTERN: &'static str = "pattern";
+const ARG_PRESET: &str = "PRESET";
+const SUB_COMMAND_CLASSIC: &str = "classic";
+const SUB_COMMAND_BRAILLE: &str = "braille";
+const SUB_COMMAND_SUBPIXEL: &str = "subpixel";
+const SUB_COMMAND_EMOJI: &str = "emoji";
+const SUB_COMMAND_PATTERN: &str = "pattern";
+const SUB_COMMAND_TEXT: &str = "text";
+const SUB_COMMAND_ANSI2IMG: &str = "ansi2img";
+const SUB_COMMAND_COMPARE: &str = "compare";
+const CLASSIC_PRESETS: [&str; 5] = ["standard", "level_10", "level_16", "level_19", "level_23"];
+/// Every image renderer, as compared by the compare command
+const RENDERERS: [&str; 8] = [
+    "standard", "level_10", "level_16", "level_19", "level_23", "block", "braille", "subpixel",
+];
+/// Bitmap pixels drawn for every column of a pattern sent through an image
+/// renderer, enough for the two dot columns of braille
+const PATTERN_PIXELS_PER_COLUMN: u32 = 4;
 const DEFAULT_NUM_COLS: u32 = 80;
 
 lazy_static! {
     static ref DEFAULT_NUM_COLS_STR: String = DEFAULT_NUM_COLS.to_string();
     static ref DEFAULT_THRESHOLD_STR: String = DEFAULT_THRESHOLD.to_string();
+    static ref DEFAULT_CELL_ASPECT_STR: String = DEFAULT_CELL_ASPECT.to_string();
+    static ref DEFAULT_STDIN_LIMIT_STR: String = (DEFAULT_MEMORY_LIMIT >> 20).to_string();
+    static ref DEFAULT_FRAME_RATE_STR: String = DEFAULT_FRAME_RATE.to_string();
+    static ref FONT_HELP: String = format!(
+        "Font: {}, the path of a .flf file, or of a .ttf or .otf file drawn through a renderer",
+        FONT_NAMES.join(", ")
+    );
     static ref ARG_IMAGE_PATH: Arg<'static> = {
         Arg::new("IMAGE_PATH")
-            .help("Image path")
             .takes_value(true)
-            .required_unless_present("STDIN")
+            .help("Image paths, directories or glob patterns")
+            .multiple_values(true)
+            .required_unless_present_any(["STDIN", "Y4M", "RAW"])
     };
     static ref ARG_NUM_COLS: Arg<'static> = {
         Arg::new("NUM_COLS")
@@ -62,53 +113,763 @@ lazy_static! {
             .help("Insert color")
             .use_value_delimiter(false)
     };
+    static ref ARG_RENDERER: Arg<'static> = {
+        Arg::new("RENDERER")
+            .long("renderer")
+            .help("Draw as a bitmap through an image renderer")
+            .takes_value(true)
+            .possible_values(RENDERERS)
+    };
     static ref ARG_STDIN: Arg<'static> = {
         Arg::new("STDIN")
             .long("stdin")
             .help("Read image from Stdin")
     };
+    static ref ARG_STDIN_LIMIT: Arg<'static> = {
+        Arg::new("STDIN_LIMIT")
+            .long("stdin-limit")
+            .help("MiB of stdin to keep in memory, larger input spills to a temporary file")
+            .takes_value(true)
+            .default_value(DEFAULT_STDIN_LIMIT_STR.as_str())
+            .use_value_delimiter(false)
+    };
+    static ref ARG_BACKGROUND: Arg<'static> = {
+        Arg::new("BACKGROUND")
+            .long("background")
+            .short('b')
+            .help("Composite transparent pixels onto a color (e.g. #ffffff), otherwise transparent cells keep the terminal background")
+            .takes_value(true)
+            .use_value_delimiter(false)
+    };
+    static ref ARGS_BATCH: [Arg<'static>; 3] = [
+        Arg::new("OUTPUT_DIR")
+            .long("output-dir")
+            .help("Write one file per input into this directory")
+            .takes_value(true)
+            .conflicts_with("STDIN"),
+        Arg::new("NAME_TEMPLATE")
+            .long("name")
+            .help("File name template for --output-dir: {stem} is the input name without extension, {ext} its extension, {parent} its directory and {format} the output extension")
+            .takes_value(true)
+            .default_value(DEFAULT_NAME_TEMPLATE)
+            .requires("OUTPUT_DIR"),
+        Arg::new("JOBS")
+            .long("jobs")
+            .short('j')
+            .help("Number of images to render in parallel, 0 uses every core")
+            .takes_value(true)
+            .default_value("1"),
+    ];
+    static ref ARGS_OUTPUT: [Arg<'static>; 3] = [
+        Arg::new("OUTPUT")
+            .long("output")
+            .short('o')
+            .help("Write to a file, the extension selects the format: .txt .ans .html .svg .png .cast")
+            .takes_value(true)
+            .conflicts_with("OUTPUT_DIR"),
+        Arg::new("FORMAT")
+            .long("format")
+            .help("Output format, overrides the extension of --output. Defaults to ansi on stdout and text in files")
+            .takes_value(true)
+            .possible_values(["text", "ansi", "ansi-art", "html", "svg", "png", "asciicast"]),
+        Arg::new("KEEP_ESCAPES")
+            .long("keep-escapes")
+            .help("Allow ANSI escape codes in plain text output"),
+    ];
+    static ref ARGS_METADATA: [Arg<'static>; 4] = [
+        Arg::new("TITLE")
+            .long("title")
+            .help("Title stored in .ans, .html, .svg and .cast files")
+            .takes_value(true),
+        Arg::new("AUTHOR")
+            .long("author")
+            .help("Author stored in the SAUCE record of .ans files")
+            .takes_value(true),
+        Arg::new("GROUP")
+            .long("group")
+            .help("Group stored in the SAUCE record of .ans files")
+            .takes_value(true),
+        Arg::new("SAUCE_FONT")
+            .long("sauce-font")
+            .help("Font name stored in the SAUCE record of .ans files")
+            .takes_value(true)
+            .default_value(DEFAULT_SAUCE_FONT),
+    ];
+    static ref ARGS_SIZE: [Arg<'static>; 3] = [
+        Arg::new("NUM_ROWS")
+            .long("height")
+            .visible_alias("rows")
+            .help("Number of rows, the width follows unless --width is also given")
+            .takes_value(true)
+            .use_value_delimiter(false),
+        Arg::new("FIT")
+            .long("fit")
+            .short('f')
+            .help("Fit the output to the terminal")
+            .takes_value(true)
+            .possible_values(["none", "auto", "fit", "width", "height"])
+            .default_value("auto"),
+        Arg::new("CELL_ASPECT")
+            .long("cell-aspect")
+            .help("Width / height ratio of a terminal cell, e.g. 0.45 for fonts with tall cells")
+            .takes_value(true)
+            .default_value(DEFAULT_CELL_ASPECT_STR.as_str()),
+    ];
+    static ref ARGS_ANIMATION: [Arg<'static>; 3] = [
+        Arg::new("FRAMES")
+            .long("frames")
+            .help("Zero based frames of an animation to render, e.g. 0-4,8,10-")
+            .takes_value(true)
+            .use_value_delimiter(false),
+        Arg::new("LOOP")
+            .long("loop")
+            .help("Number of times to play an animation, 0 loops forever, defaults to the count stored in the file or 1")
+            .takes_value(true),
+        Arg::new("NO_PLAY")
+            .long("no-play")
+            .help("Write all frames of an animation one after another instead of playing them"),
+    ];
+    static ref ARGS_STREAM: [Arg<'static>; 3] = [
+        Arg::new("Y4M")
+            .long("y4m")
+            .help("Play a YUV4MPEG2 video stream, read from stdin unless an image path is given")
+            .conflicts_with("RAW"),
+        Arg::new("RAW")
+            .long("raw")
+            .help("Play headerless rgb24 video frames of the given size, e.g. 320x240")
+            .takes_value(true)
+            .value_name("WxH")
+            .use_value_delimiter(false),
+        Arg::new("FPS")
+            .long("fps")
+            .help("Frame rate of a raw video stream")
+            .takes_value(true)
+            .default_value(DEFAULT_FRAME_RATE_STR.as_str())
+            .requires("RAW"),
+    ];
+    static ref ARGS_PATTERN_ANIMATION: [Arg<'static>; 2] = [
+        Arg::new("FPS")
+            .long("fps")
+            .help("Frame rate of an animated pattern")
+            .takes_value(true)
+            .default_value(DEFAULT_FRAME_RATE_STR.as_str()),
+        Arg::new("DURATION")
+            .long("duration")
+            .help("Seconds to animate the pattern: fractals zoom in, noise and plasma scroll, life and automaton evolve")
+            .takes_value(true),
+    ];
+    static ref ARGS_COLORIZE: [Arg<'static>; 2] = [
+        Arg::new("COLORIZE")
+            .long("colorize")
+            .help("Tint every character, whatever the source colors: rainbow, a gradient like viridis or #000,#f00, or a fixed palette like palette:#f00,#0f0,#00f")
+            .takes_value(true),
+        Arg::new("COLORIZE_DIRECTION")
+            .long("colorize-direction")
+            .help("Which way the tint runs")
+            .takes_value(true)
+            .possible_values(["horizontal", "vertical", "diagonal", "radial"])
+            .default_value("horizontal")
+            .requires("COLORIZE"),
+    ];
+    static ref ARGS_TRANSFORM: [Arg<'static>; 3] = [
+        Arg::new("CROP")
+            .long("crop")
+            .help("Region of interest as x,y,w,h")
+            .takes_value(true)
+            .use_value_delimiter(false),
+        Arg::new("ROTATE")
+            .long("rotate")
+            .help("Rotate clockwise")
+            .takes_value(true)
+            .possible_values(["90", "180", "270"]),
+        Arg::new("FLIP")
+            .long("flip")
+            .help("Flip horizontally or vertically")
+            .takes_value(true)
+            .possible_values(["h", "v"]),
+    ];
+    static ref ARGS_FRACTAL: [Arg<'static>; 4] = [
+        Arg::new("CENTER")
+            .long("center")
+            .help("Point of the complex plane in the middle, e.g. -0.75,0.1")
+            .takes_value(true)
+            .allow_hyphen_values(true),
+        Arg::new("C")
+            .long("c")
+            .help("Constant of the julia preset, e.g. -0.4,0.6")
+            .takes_value(true)
+            .allow_hyphen_values(true),
+        Arg::new("ZOOM")
+            .long("zoom")
+            .help("Magnification, 1 shows the whole fractal")
+            .takes_value(true),
+        Arg::new("ITERATIONS")
+            .long("iterations")
+            .help("Maximum iterations per point, more show finer detail when zoomed in")
+            .takes_value(true),
+    ];
+    static ref ARGS_PATTERN_STYLE: [Arg<'static>; 3] = [
+        Arg::new("CHARSET")
+            .long("charset")
+            .help("Characters from low to high values, or from fast escape to inside a fractal: ascii, density, blocks or the characters themselves")
+            .takes_value(true)
+            .allow_hyphen_values(true),
+        Arg::new("GRADIENT")
+            .long("gradient")
+            .help("Truecolor gradient: viridis, magma, fire, grayscale or stops like #000,#f00@0.3,#ff0")
+            .takes_value(true),
+        Arg::new("HALF_BLOCKS")
+            .long("half-blocks")
+            .help("Draw two colored points per cell with half blocks instead of characters"),
+    ];
+    static ref ARGS_PROCEDURAL: [Arg<'static>; 4] = [
+        Arg::new("SEED")
+            .long("seed")
+            .help("Seed of the noise, life, maze and plasma presets, random when not given")
+            .takes_value(true),
+        Arg::new("SCALE")
+            .long("scale")
+            .help("Noise features or plasma waves across the width")
+            .takes_value(true),
+        Arg::new("GENERATIONS")
+            .long("generations")
+            .help("Steps the life preset runs from a random soup")
+            .takes_value(true),
+        Arg::new("RULE")
+            .long("rule")
+            .help("Wolfram rule number of the automaton preset, 0 to 255")
+            .takes_value(true),
+    ];
+    static ref ARGS_PREPROCESS: [Arg<'static>; 7] = [
+        Arg::new("AUTO_LEVELS")
+            .long("auto-levels")
+            .help("Stretch each channel to the full 0-255 range"),
+        Arg::new("EQUALIZE")
+            .long("equalize")
+            .help("Histogram equalization"),
+        Arg::new("BRIGHTNESS")
+            .long("brightness")
+            .help("Add to every channel, e.g. -20 or 20")
+            .takes_value(true)
+            .allow_hyphen_values(true),
+        Arg::new("CONTRAST")
+            .long("contrast")
+            .help("Contrast adjustment in percent, e.g. -10 or 30")
+            .takes_value(true)
+            .allow_hyphen_values(true),
+        Arg::new("GAMMA")
+            .long("gamma")
+            .help("Gamma correction, values above 1 brighten mid tones")
+            .takes_value(true),
+        Arg::new("BLUR")
+            .long("blur")
+            .help("Gaussian blur sigma")
+            .takes_value(true),
+        Arg::new("SHARPEN")
+            .long("sharpen")
+            .help("Unsharp mask sigma")
+            .takes_value(true),
+    ];
+}
+
+/// Decode the image at `image_path`, or stdin when there is none, with the
+/// loop count stored in it.
+fn read_frames(
+    matches: &ArgMatches,
+    image_path: Option<&Path>,
+) -> Result<(Vec<Frame<DynamicImage>>, Option<u32>), UnicodeArtError> {
+    let mut transform = matches.transform();
+    let (frames, loop_count) = if let Some(image_path) = image_path {
+        // phone photos are stored sideways with an EXIF hint
+        transform.orientation = exif_orientation(&mut BufReader::new(File::open(image_path)?));
+        decode_frames(BufReader::new(File::open(image_path)?))?
+    } else {
+        let mut input = Input::new(stdin().lock(), matches.stdin_limit())?;
+        transform.orientation = exif_orientation(&mut input);
+        input.seek(SeekFrom::Start(0))?;
+        decode_frames(input)?
+    };
+    let frames = match matches.frame_selection() {
+        Some(selection) => selection.select(frames),
+        None => frames,
+    };
+    let prepare = image_pipeline(matches, transform);
+    let frames = frames
+        .into_iter()
+        .map(|frame| frame.map(&prepare))
+        .collect::<Result<_, _>>()?;
+    Ok((frames, loop_count))
+}
+
+/// Geometric transforms, background compositing and tonal adjustments applied
+/// to every decoded frame before it is rendered.
+fn image_pipeline(
+    matches: &ArgMatches,
+    transform: Transform,
+) -> impl Fn(DynamicImage) -> Result<DynamicImage, UnicodeArtError> {
+    let background = matches.background();
+    let preprocess = matches.preprocess();
+    move |image| {
+        let image = transform.apply(image)?;
+        let image = match background {
+            Some(background) => image.composite(background),
+            None => image,
+        };
+        Ok(preprocess.apply(image))
+    }
+}
+
+/// Video frames piped in as Y4M or raw rgb24, from a file or stdin.
+fn open_stream(
+    matches: &ArgMatches,
+    image_path: Option<&Path>,
+) -> Result<Box<dyn FrameStream>, UnicodeArtError> {
+    let reader: Box<dyn BufRead> = match image_path {
+        Some(image_path) => Box::new(BufReader::new(File::open(image_path)?)),
+        None => Box::new(stdin().lock()),
+    };
+    Ok(match matches.raw_size() {
+        Some((width, height)) => Box::new(RawRgbStream::new(
+            reader,
+            width,
+            height,
+            matches.frame_rate(),
+        )),
+        None => Box::new(Y4mStream::new(reader)?),
+    })
 }
 
-fn get_img2_txt_impl<'a>(
-    name: &'a str,
+/// Render one input. Animations and video are only played when `interactive`,
+/// otherwise their frames are written one after another.
+fn write_art(
+    option: &dyn UnicodeArtOption,
+    matches: &ArgMatches,
+    image_path: Option<&Path>,
+    format: OutputFormat,
+    interactive: bool,
+    writer: &mut dyn Write,
+) -> Result<(), UnicodeArtError> {
+    let is_stream = matches.is_present("Y4M") || matches.is_present("RAW");
+    let interactive = interactive && !matches.is_present("NO_PLAY");
+    if is_stream && format == OutputFormat::Ansi {
+        let prepare = image_pipeline(matches, matches.transform());
+        let mut stream = open_stream(matches, image_path)?;
+        StreamPlayer::new(interactive).play(stream.as_mut(), option, &prepare, writer)?;
+        return Ok(());
+    }
+    let (frames, stored_loop_count) = match is_stream {
+        true => {
+            let prepare = image_pipeline(matches, matches.transform());
+            let mut stream = open_stream(matches, image_path)?;
+            let max_frames = if format.is_still() { 1 } else { usize::MAX };
+            let frames = render_stream(stream.as_mut(), option, &prepare, max_frames)?;
+            (frames, None)
+        }
+        false => {
+            let (frames, loop_count) = read_frames(matches, image_path)?;
+            (render_frames(option, frames)?, loop_count)
+        }
+    };
+    match interactive && format == OutputFormat::Ansi && frames.len() > 1 {
+        true => {
+            let loop_count = matches.loop_count().or(stored_loop_count).unwrap_or(1);
+            Player::new(loop_count).play(&frames, writer)
+        }
+        false => format.write(&frames, &matches.metadata(), writer),
+    }
+}
+
+/// Render a single input to stdout or a file, or every input into the output
+/// directory.
+fn run(option: &dyn UnicodeArtOption, matches: &ArgMatches) -> Result<(), UnicodeArtError> {
+    let colorized;
+    let option = match matches.colorizer() {
+        Some(colorizer) => {
+            colorized = ColorizedOption::new(option, colorizer);
+            &colorized as &dyn UnicodeArtOption
+        }
+        None => option,
+    };
+    // an empty directory or glob must not fall back to reading stdin
+    let inputs = match matches.values_of("IMAGE_PATH") {
+        Some(values) => expand_inputs(values).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            exit(exitcode::USAGE)
+        }),
+        None => Vec::new(),
+    };
+    let output_dir = match (matches.value_of("OUTPUT_DIR"), matches.value_of("OUTPUT")) {
+        // stdin has no name to write it under
+        (Some(_), _) if inputs.is_empty() => {
+            eprintln!("--output-dir needs image paths, write stdin with --output");
+            exit(exitcode::USAGE)
+        }
+        (Some(output_dir), _) => Path::new(output_dir),
+        (None, _) if inputs.len() > 1 => {
+            eprintln!("Rendering several images needs --output-dir");
+            exit(exitcode::USAGE)
+        }
+        (None, Some(output)) => {
+            let format = matches.output_format(OutputFormat::Text);
+            let image_path = inputs.first().map(PathBuf::as_path);
+            return write_atomic(Path::new(output), |writer| {
+                write_art(option, matches, image_path, format, false, writer)
+            });
+        }
+        (None, None) => {
+            let format = matches.output_format(OutputFormat::Ansi);
+            let mut buf = BufWriter::new(stdout());
+            let image_path = inputs.first().map(PathBuf::as_path);
+            write_art(option, matches, image_path, format, is_terminal(), &mut buf)?;
+            return Ok(buf.flush()?);
+        }
+    };
+    fs::create_dir_all(output_dir)?;
+    let template = matches
+        .value_of("NAME_TEMPLATE")
+        .unwrap_or(DEFAULT_NAME_TEMPLATE);
+    let format = matches.output_format(OutputFormat::Text);
+    let names = output_names(template, &inputs, format.extension()).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        exit(exitcode::USAGE)
+    });
+    let names: HashMap<_, _> = inputs.iter().map(PathBuf::as_path).zip(names).collect();
+    let failures = run_batch(&inputs, matches.jobs(), |image_path| {
+        let name = &names[image_path];
+        write_atomic(&output_dir.join(name), |writer| {
+            write_art(option, matches, Some(image_path), format, false, writer)
+        })
+    });
+    if failures.is_empty() {
+        return Ok(());
+    }
+    for (image_path, err) in &failures {
+        eprintln!("{}: {}", image_path.display(), err);
+    }
+    eprintln!("{} of {} images failed", failures.len(), inputs.len());
+    exit(exitcode::DATAERR)
+}
+
+/// Draw a pattern by itself, or as a bitmap through an image renderer.
+fn draw_pattern(
+    pattern: &dyn PatternOption,
+    renderer: Option<&dyn UnicodeArtOption>,
+    num_cols: u32,
+    writer: &mut dyn Write,
+) -> Result<(), UnicodeArtError> {
+    match renderer {
+        Some(renderer) => {
+            let image = pattern.to_image(num_cols * PATTERN_PIXELS_PER_COLUMN)?;
+            let art = renderer.new_unicode_art(&image)?;
+            art.write_all(writer)
+        }
+        None => pattern.new_pattern_art()?.write_all(writer),
+    }
+}
+
+/// Render a pattern, or every frame of its animation, to stdout or a file.
+fn write_pattern(
+    option: &dyn AnimatedOption,
+    renderer: Option<&dyn UnicodeArtOption>,
+    num_cols: u32,
+    matches: &ArgMatches,
+) -> Result<(), UnicodeArtError> {
+    let draw = |t: f64, writer: &mut dyn Write| {
+        draw_pattern(option.at_time(t).as_ref(), renderer, num_cols, writer)
+    };
+    let frames = animate(matches.frame_rate(), matches.duration(), draw)?;
+    let frames = match matches.frame_selection() {
+        Some(selection) => selection.select(frames),
+        None => frames,
+    };
+    write_frames(&frames, matches)
+}
+
+/// Render a text banner, or any other still art, to stdout or a file.
+fn write_still<F>(draw: F, matches: &ArgMatches) -> Result<(), UnicodeArtError>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), UnicodeArtError>,
+{
+    let mut buf = Vec::new();
+    draw(&mut buf)?;
+    let frame = Frame::new(String::from_utf8_lossy(&buf).into_owned(), Duration::ZERO);
+    write_frames(&[frame], matches)
+}
+
+/// Write generated frames to `--output`, or play them on the terminal.
+fn write_frames(frames: &[Frame<String>], matches: &ArgMatches) -> Result<(), UnicodeArtError> {
+    let colorized: Vec<_>;
+    let frames = match matches.colorizer() {
+        Some(colorizer) => {
+            colorized = frames
+                .iter()
+                .map(|frame| Frame::new(colorizer.colorize(&frame.content), frame.delay))
+                .collect();
+            &colorized
+        }
+        None => frames,
+    };
+    let write = |format: OutputFormat, interactive: bool, writer: &mut dyn Write| {
+        // only commands that animate take the playback options
+        let is_animation = frames.len() > 1 && interactive && format == OutputFormat::Ansi;
+        match is_animation && !matches.is_present("NO_PLAY") {
+            true => Player::new(matches.loop_count().unwrap_or(1)).play(frames, writer),
+            false => format.write(frames, &matches.metadata(), writer),
+        }
+    };
+    match matches.value_of("OUTPUT") {
+        Some(output) => write_atomic(Path::new(output), |writer| {
+            write(matches.output_format(OutputFormat::Text), false, writer)
+        }),
+        None => {
+            let mut buf = BufWriter::new(stdout());
+            write(
+                matches.output_format(OutputFormat::Ansi),
+                is_terminal(),
+                &mut buf,
+            )?;
+            Ok(buf.flush()?)
+        }
+    }
+}
+
+/// Parse ANSI or text art from a file or stdin back into an image.
+fn reverse_art(matches: &ArgMatches) -> Result<(), UnicodeArtError> {
+    let format = matches.image_format();
+    let bytes = match matches.value_of("INPUT_PATH") {
+        Some(input_path) => fs::read(input_path)?,
+        None => {
+            let mut bytes = Vec::new();
+            stdin().lock().read_to_end(&mut bytes)?;
+            bytes
+        }
+    };
+    let image = ansi_to_image(&bytes, matches.reverse_mode(), matches.is_present("INVERT"))?;
+    let output = matches.value_of("OUTPUT").unwrap_or_default();
+    write_atomic(Path::new(output), |writer| {
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, format)?;
+        Ok(writer.write_all(bytes.get_ref())?)
+    })
+}
+
+/// Score every render mode on one image and print a table.
+fn compare_modes(matches: &ArgMatches) -> Result<(), UnicodeArtError> {
+    let num_cols = matches.num_cols(MIN_NUM_COLS, DEFAULT_NUM_COLS);
+    let is_color = matches.is_present("COLOR");
+    let is_invert = matches.is_present("INVERT");
+    // scores shouldn't depend on the terminal
+    let aspect_ratio = matches.aspect_ratio_with(num_cols, TermFit::None);
+    let image_path = matches.value_of("IMAGE_PATH").unwrap_or_default();
+    let image = image::io::Reader::open(image_path)?
+        .with_guessed_format()?
+        .decode()?;
+
+    let options = RENDERERS
+        .iter()
+        .map(|name| {
+            let option = get_renderer_impl(name, num_cols, is_color, is_invert, aspect_ratio)?;
+            Ok((*name, option))
+        })
+        .collect::<Result<Vec<_>, UnicodeArtError>>()?;
+    let candidates: Vec<(&str, &dyn UnicodeArtOption)> = options
+        .iter()
+        .map(|(name, option)| (*name, option.as_ref()))
+        .collect();
+
+    let mut buf = BufWriter::new(stdout());
+    writeln!(buf, "{:<10} {:>7} {:>6}", "mode", "psnr", "ssim")?;
+    for score in compare(&image, &candidates, is_invert)? {
+        writeln!(
+            buf,
+            "{:<10} {:>7.2} {:>6.3}",
+            score.name, score.psnr, score.ssim
+        )?;
+    }
+    Ok(buf.flush()?)
+}
+
+fn get_img2_txt_impl(
+    name: &str,
     num_cols: u32,
     is_color: bool,
     is_invert: bool,
+    aspect_ratio: SimpleAspectRatio,
 ) -> Result<Box<dyn UnicodeArtOption>, UnicodeArtError> {
     let option: Box<dyn UnicodeArtOption> = match name {
-        "standard" => Box::new(ClassicAsciiArtOption::new_standard(
-            num_cols, is_color, is_invert,
-        )),
-        "level_10" => Box::new(ClassicAsciiArtOption::new_level_10(
-            num_cols, is_color, is_invert,
-        )),
-        "level_19" => Box::new(ClassicAsciiArtOption::new_level_19(
-            num_cols, is_color, is_invert,
-        )),
-        "level_16" => Box::new(ClassicAsciiArtOption::new_level_16(
-            num_cols, is_color, is_invert,
-        )),
-        "level_23" => Box::new(ClassicAsciiArtOption::new_level_23(
-            num_cols, is_color, is_invert,
-        )),
-        "block" => Box::new(BlockUnicodeArtOption::new(num_cols, is_color)),
+        "standard" => Box::new(
+            ClassicAsciiArtOption::new_standard(num_cols, is_color, is_invert)
+                .with_aspect_ratio(aspect_ratio),
+        ),
+        "level_10" => Box::new(
+            ClassicAsciiArtOption::new_level_10(num_cols, is_color, is_invert)
+                .with_aspect_ratio(aspect_ratio),
+        ),
+        "level_19" => Box::new(
+            ClassicAsciiArtOption::new_level_19(num_cols, is_color, is_invert)
+                .with_aspect_ratio(aspect_ratio),
+        ),
+        "level_16" => Box::new(
+            ClassicAsciiArtOption::new_level_16(num_cols, is_color, is_invert)
+                .with_aspect_ratio(aspect_ratio),
+        ),
+        "level_23" => Box::new(
+            ClassicAsciiArtOption::new_level_23(num_cols, is_color, is_invert)
+                .with_aspect_ratio(aspect_ratio),
+        ),
+        "block" => {
+            Box::new(BlockUnicodeArtOption::new(num_cols, is_color).with_aspect_ratio(aspect_ratio))
+        }
         _ => return Err(UnicodeArtError::UnsupportError),
     };
     Ok(option)
 }
 
-fn get_patten_impl<'a>(
-    name: &'a str,
-    _num_cols: u32,
+/// Any of the image renderers by name.
+fn get_renderer_impl(
+    name: &str,
+    num_cols: u32,
+    is_color: bool,
+    is_invert: bool,
+    aspect_ratio: SimpleAspectRatio,
 ) -> Result<Box<dyn UnicodeArtOption>, UnicodeArtError> {
-    let option: Box<dyn UnicodeArtOption> = match name {
-        "mandel" => Box::new(MandelAsciiArtOption::new()),
+    Ok(match name {
+        "braille" => Box::new(
+            BrailleAsciiArtOption::new(num_cols, DEFAULT_THRESHOLD, is_color, is_invert)
+                .with_aspect_ratio(aspect_ratio),
+        ),
+        "subpixel" => Box::new(
+            SubpixelUnicodeArtOption::new(num_cols, is_invert).with_aspect_ratio(aspect_ratio),
+        ),
+        name => get_img2_txt_impl(name, num_cols, is_color, is_invert, aspect_ratio)?,
+    })
+}
+
+fn get_patten_impl(
+    name: &str,
+    num_cols: u32,
+    matches: &ArgMatches,
+) -> Result<Box<dyn AnimatedOption>, UnicodeArtError> {
+    let fractal = match name {
+        "mandel" => Fractal::Mandelbrot,
+        "julia" => Fractal::Julia(matches.julia_c()),
+        "burning_ship" => Fractal::BurningShip,
+        "tricorn" => Fractal::Tricorn,
+        "newton" => Fractal::Newton,
+        _ => return get_procedural_impl(name, num_cols, matches),
+    };
+    let aspect_ratio = matches.aspect_ratio_with(num_cols, matches.termfit());
+    let mut option = FractalAsciiArtOption::new(fractal, num_cols)
+        .with_aspect_ratio(aspect_ratio)
+        .with_zoom(matches.zoom())
+        .with_iterations(matches.iterations())
+        .with_half_blocks(matches.is_present("HALF_BLOCKS"));
+    // zoom sequences head for the edge of the set
+    match matches.center() {
+        Some(center) => option = option.with_center(center),
+        None if !matches.duration().is_zero() => option = option.with_center(fractal.zoom_target()),
+        None => {}
+    }
+    if let Some(charset) = matches.charset() {
+        option = option.with_charset(charset);
+    }
+    if let Some(gradient) = matches.gradient() {
+        option = option.with_gradient(gradient);
+    }
+    Ok(Box::new(option))
+}
+
+fn get_procedural_impl(
+    name: &str,
+    num_cols: u32,
+    matches: &ArgMatches,
+) -> Result<Box<dyn AnimatedOption>, UnicodeArtError> {
+    let procedural = match name {
+        "noise" => Procedural::Noise,
+        "simplex" => Procedural::Simplex,
+        "plasma" => Procedural::Plasma,
+        "life" => Procedural::Life,
+        "automaton" => Procedural::Automaton(matches.rule()),
+        "maze" => Procedural::Maze,
         _ => return Err(UnicodeArtError::UnsupportError),
     };
+    let aspect_ratio = matches.aspect_ratio_with(num_cols, matches.termfit());
+    let mut option = ProceduralAsciiArtOption::new(procedural, num_cols)
+        .with_aspect_ratio(aspect_ratio)
+        .with_seed(matches.seed())
+        .with_scale(matches.scale())
+        .with_generations(matches.generations())
+        .with_half_blocks(matches.is_present("HALF_BLOCKS"));
+    if let Some(charset) = matches.charset() {
+        option = option.with_charset(charset);
+    }
+    if let Some(gradient) = matches.gradient() {
+        option = option.with_gradient(gradient);
+    }
+    Ok(Box::new(option))
+}
+
+/// The words given on the command line, or all of stdin.
+fn read_text(matches: &ArgMatches) -> Result<String, UnicodeArtError> {
+    match matches.values_of("TEXT") {
+        Some(words) => Ok(words.collect::<Vec<_>>().join(" ")),
+        None => {
+            let mut text = String::new();
+            stdin().lock().read_to_string(&mut text)?;
+            Ok(text)
+        }
+    }
+}
+
+fn get_text_impl(
+    text: &str,
+    num_cols: u32,
+    matches: &ArgMatches,
+) -> Result<FigletAsciiArtOption, UnicodeArtError> {
+    let mut option =
+        FigletAsciiArtOption::new(matches.font(), text, num_cols).with_justify(matches.justify());
+    if let Some(layout) = matches.layout() {
+        option = option.with_layout(layout);
+    }
+    if let Some(gradient) = matches.gradient() {
+        option = option.with_gradient(gradient);
+    }
     Ok(option)
 }
 
-fn main() -> Result<(), UnicodeArtError> {
+fn get_ttf_impl(data: &[u8], text: &str, matches: &ArgMatches) -> Result<TtfText, UnicodeArtError> {
+    let mut option = TtfText::new(data, text)?
+        .with_pixel_size(matches.pixel_size())
+        .with_justify(matches.justify());
+    if let Some(gradient) = matches.gradient() {
+        option = option.with_gradient(gradient);
+    }
+    Ok(option)
+}
+
+/// Exit code of a failed command, following sysexits.
+fn exit_code(err: &UnicodeArtError) -> i32 {
+    match err {
+        UnicodeArtError::UnsupportError | UnicodeArtError::InvalidArgumentError(_) => {
+            exitcode::USAGE
+        }
+        UnicodeArtError::ImageError(_) => exitcode::DATAERR,
+        UnicodeArtError::IoError(err) if err.kind() == io::ErrorKind::InvalidData => {
+            exitcode::DATAERR
+        }
+        UnicodeArtError::IoError(err) if err.kind() == io::ErrorKind::NotFound => exitcode::NOINPUT,
+        UnicodeArtError::IoError(_) => exitcode::IOERR,
+    }
+}
+
+fn main() {
+    if let Err(err) = run_command() {
+        eprintln!("Error: {}", err);
+        exit(exit_code(&err))
+    }
+}
+
+fn run_command() -> Result<(), UnicodeArtError> {
     let matches = Command::new("unicode_art")
         .about("A Unicode art generator")
         .subcommand_required(true)
@@ -119,6 +880,7 @@ fn main() -> Result<(), UnicodeArtError> {
             Command::new(SUB_COMMAND_CLASSIC)
                 .about("Generate ASCII art from image")
                 .arg(ARG_STDIN.clone())
+                .arg(ARG_STDIN_LIMIT.clone())
                 .arg(ARG_IMAGE_PATH.clone())
                 .arg(
                     Arg::new(ARG_PRESET)
@@ -127,21 +889,30 @@ fn main() -> Result<(), UnicodeArtError> {
                         .help("Preset chars list")
                         .default_value("standard")
                         .default_missing_value("standard")
-                        .possible_values([
-                            "standard", "level_10", "level_16", "level_19", "level_23", "block",
-                        ])
+                        .possible_values(CLASSIC_PRESETS.iter().chain(["block"].iter()))
                         .takes_value(true)
                         .use_value_delimiter(false),
                 )
                 .arg(ARG_NUM_COLS.clone())
+                .args(ARGS_SIZE.iter())
                 .arg(ARG_COLOR.clone())
                 .arg(ARG_INVERT.clone())
+                .arg(ARG_BACKGROUND.clone())
+                .args(ARGS_COLORIZE.iter())
+                .args(ARGS_TRANSFORM.iter())
+                .args(ARGS_ANIMATION.iter())
+                .args(ARGS_STREAM.iter())
+                .args(ARGS_OUTPUT.iter())
+                .args(ARGS_METADATA.iter())
+                .args(ARGS_BATCH.iter())
+                .args(ARGS_PREPROCESS.iter())
                 .arg_required_else_help(true),
         )
         .subcommand(
             Command::new(SUB_COMMAND_BRAILLE)
                 .about("Generate Braille Unicode art from image")
                 .arg(ARG_STDIN.clone())
+                .arg(ARG_STDIN_LIMIT.clone())
                 .arg(ARG_IMAGE_PATH.clone())
                 .arg(
                     Arg::new("THRESHOLD")
@@ -154,125 +925,328 @@ fn main() -> Result<(), UnicodeArtError> {
                         .use_value_delimiter(false),
                 )
                 .arg(ARG_NUM_COLS.clone())
+                .args(ARGS_SIZE.iter())
                 .arg(ARG_COLOR.clone())
                 .arg(ARG_INVERT.clone())
+                .arg(ARG_BACKGROUND.clone())
+                .args(ARGS_COLORIZE.iter())
+                .args(ARGS_TRANSFORM.iter())
+                .args(ARGS_ANIMATION.iter())
+                .args(ARGS_STREAM.iter())
+                .args(ARGS_OUTPUT.iter())
+                .args(ARGS_METADATA.iter())
+                .args(ARGS_BATCH.iter())
+                .args(ARGS_PREPROCESS.iter())
                 .arg_required_else_help(true),
         )
         .subcommand(
             Command::new(SUB_COMMAND_SUBPIXEL)
                 .about("Generate Subpixel Unicode art from image")
                 .arg(ARG_STDIN.clone())
+                .arg(ARG_STDIN_LIMIT.clone())
                 .arg(ARG_IMAGE_PATH.clone())
                 .arg(ARG_NUM_COLS.clone())
+                .args(ARGS_SIZE.iter())
                 .arg(ARG_COLOR.clone())
                 .arg(ARG_INVERT.clone())
+                .arg(ARG_BACKGROUND.clone())
+                .args(ARGS_COLORIZE.iter())
+                .args(ARGS_TRANSFORM.iter())
+                .args(ARGS_ANIMATION.iter())
+                .args(ARGS_STREAM.iter())
+                .args(ARGS_OUTPUT.iter())
+                .args(ARGS_METADATA.iter())
+                .args(ARGS_BATCH.iter())
+                .args(ARGS_PREPROCESS.iter())
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new(SUB_COMMAND_EMOJI)
+                .about("Generate an emoji mosaic from image")
+                .arg(ARG_STDIN.clone())
+                .arg(ARG_STDIN_LIMIT.clone())
+                .arg(ARG_IMAGE_PATH.clone())
+                .arg(ARG_NUM_COLS.clone())
+                .args(ARGS_SIZE.iter())
+                .arg(ARG_BACKGROUND.clone())
+                .args(ARGS_COLORIZE.iter())
+                .args(ARGS_TRANSFORM.iter())
+                .args(ARGS_ANIMATION.iter())
+                .args(ARGS_STREAM.iter())
+                .args(ARGS_OUTPUT.iter())
+                .args(ARGS_METADATA.iter())
+                .args(ARGS_BATCH.iter())
+                .args(ARGS_PREPROCESS.iter())
                 .arg_required_else_help(true),
         )
         .subcommand(
             Command::new(SUB_COMMAND_PATTERN)
                 .about("Generate ASCII art pattern")
-                .arg(ARG_STDIN.clone())
                 .arg(
                     Arg::new(ARG_PRESET)
                         .long("preset")
                         .short('p')
                         .help("Preset pattern")
-                        .possible_values(["mandel"])
+                        .possible_values([
+                            "mandel",
+                            "julia",
+                            "burning_ship",
+                            "tricorn",
+                            "newton",
+                            "noise",
+                            "simplex",
+                            "plasma",
+                            "life",
+                            "automaton",
+                            "maze",
+                        ])
                         .takes_value(true)
                         .default_value("mandel")
                         .default_missing_value("mandel")
                         .use_value_delimiter(false),
                 )
                 .arg(ARG_NUM_COLS.clone())
+                .args(ARGS_SIZE.iter())
+                .arg(ARG_COLOR.clone())
+                .args(ARGS_FRACTAL.iter())
+                .args(ARGS_PATTERN_STYLE.iter())
+                .arg(ARG_RENDERER.clone())
+                .args(ARGS_COLORIZE.iter())
+                .args(ARGS_PROCEDURAL.iter())
+                .args(ARGS_PATTERN_ANIMATION.iter())
+                .args(ARGS_ANIMATION.iter())
+                .arg(
+                    Arg::new("OUTPUT")
+                        .long("output")
+                        .short('o')
+                        .help("Write to a file, the extension selects the format: .txt .ans .html .svg .png .cast")
+                        .takes_value(true),
+                )
+                .args(ARGS_OUTPUT[1..].iter())
+                .args(ARGS_METADATA.iter())
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new(SUB_COMMAND_TEXT)
+                .about("Generate a text banner with a FIGlet font")
+                .arg(
+                    Arg::new("STDIN")
+                        .long("stdin")
+                        .help("Read text from Stdin"),
+                )
+                .arg(
+                    Arg::new("TEXT")
+                        .takes_value(true)
+                        .help("Text of the banner")
+                        .multiple_values(true)
+                        .required_unless_present("STDIN")
+                        .conflicts_with("STDIN"),
+                )
+                .arg(
+                    Arg::new("FONT")
+                        .long("font")
+                        .help(FONT_HELP.as_str())
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("SIZE")
+                        .long("size")
+                        .help("Pixel size of .ttf and .otf fonts before the renderer scales the text to the width")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("LAYOUT")
+                        .long("layout")
+                        .help("Spacing of the characters, defaults to the font's own")
+                        .takes_value(true)
+                        .possible_values(["full", "fitting", "smushing"]),
+                )
+                .arg(
+                    Arg::new("JUSTIFY")
+                        .long("justify")
+                        .help("Alignment of lines within the width")
+                        .takes_value(true)
+                        .possible_values(["left", "center", "right"])
+                        .default_value("left"),
+                )
+                .arg(ARG_NUM_COLS.clone().help("Number of columns, longer lines wrap"))
+                .arg(ARG_COLOR.clone())
+                .arg(ARGS_PATTERN_STYLE[1].clone())
+                .arg(ARG_RENDERER.clone())
+                .args(ARGS_COLORIZE.iter())
+                .arg(
+                    Arg::new("OUTPUT")
+                        .long("output")
+                        .short('o')
+                        .help("Write to a file, the extension selects the format: .txt .ans .html .svg .png .cast")
+                        .takes_value(true),
+                )
+                .args(ARGS_OUTPUT[1..].iter())
+                .args(ARGS_METADATA.iter())
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new(SUB_COMMAND_ANSI2IMG)
+                .about("Reconstruct an image from ANSI or Unicode art")
+                .arg(ARG_STDIN.clone())
+                .arg(
+                    Arg::new("INPUT_PATH")
+                        .takes_value(true)
+                        .help("Text or ANSI art file, UTF-8 or code page 437")
+                        .required_unless_present("STDIN"),
+                )
+                .arg(
+                    Arg::new("OUTPUT")
+                        .long("output")
+                        .short('o')
+                        .help("Image file to write, the extension selects the format")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("MODE")
+                        .long("mode")
+                        .help("block and braille restore the sampled pixels, glyph draws every character")
+                        .takes_value(true)
+                        .possible_values(["auto", "block", "braille", "glyph"])
+                        .default_value("auto"),
+                )
+                .arg(ARG_INVERT.clone())
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new(SUB_COMMAND_COMPARE)
+                .about("Score every render mode against an image with PSNR and SSIM")
+                .arg(
+                    Arg::new("IMAGE_PATH")
+                        .takes_value(true)
+                        .help("Image path")
+                        .required(true),
+                )
+                .arg(ARG_NUM_COLS.clone())
+                .arg(ARGS_SIZE[0].clone())
+                .arg(ARGS_SIZE[2].clone())
+                .arg(ARG_COLOR.clone())
+                .arg(ARG_INVERT.clone())
                 .arg_required_else_help(true),
         )
         .get_matches();
 
-    let mut buf = BufWriter::new(stdout());
     match matches.subcommand() {
         Some(("classic", sub_matches)) => {
             let num_cols = sub_matches.num_cols(MIN_NUM_COLS, DEFAULT_NUM_COLS);
             let is_color = sub_matches.is_present("COLOR");
             let is_invert = sub_matches.is_present("INVERT");
-            let is_stdin = sub_matches.is_present("STDIN");
-
-            let image = if is_stdin {
-                Reader::new(Input::stdin(stdin()))
-                    .with_guessed_format()?
-                    .decode()?
-            } else {
-                let image_path = sub_matches
-                    .value_of("IMAGE_PATH")
-                    .expect("Missing image path");
-                Reader::open(image_path).unwrap().decode()?
-            };
-
-            sub_matches
+            let aspect_ratio = sub_matches.aspect_ratio(num_cols);
+            let option = sub_matches
                 .value_of(ARG_PRESET)
                 .map_or(Err(UnicodeArtError::UnsupportError), |name| {
-                    get_img2_txt_impl(name, num_cols, is_color, is_invert)
-                })?
-                .new_unicode_art(&image)?
-                .write_all(&mut buf)?;
-            Ok(())
+                    get_img2_txt_impl(name, num_cols, is_color, is_invert, aspect_ratio)
+                })?;
+            run(option.as_ref(), sub_matches)
         }
         Some(("pattern", sub_matches)) => {
             let num_cols = sub_matches.num_cols(MIN_NUM_COLS, DEFAULT_NUM_COLS);
-            let image = DynamicImage::ImageRgb8(RgbImage::new(1, 1));
-
-            sub_matches
+            let option = sub_matches
                 .value_of(ARG_PRESET)
                 .map_or(Err(UnicodeArtError::UnsupportError), |name| {
-                    get_patten_impl(name, num_cols)
-                })?
-                .new_unicode_art(&image)?
-                .write_all(&mut buf)?;
-            Ok(())
+                    get_patten_impl(name, num_cols, sub_matches)
+                })?;
+            let renderer = match sub_matches.value_of("RENDERER") {
+                Some(name) => {
+                    let is_color = sub_matches.is_present("COLOR");
+                    let aspect_ratio =
+                        sub_matches.aspect_ratio_with(num_cols, sub_matches.termfit());
+                    Some(get_renderer_impl(
+                        name,
+                        num_cols,
+                        is_color,
+                        false,
+                        aspect_ratio,
+                    )?)
+                }
+                None => None,
+            };
+            write_pattern(option.as_ref(), renderer.as_deref(), num_cols, sub_matches)
+        }
+        Some(("text", sub_matches)) => {
+            let num_cols = sub_matches.num_cols(MIN_NUM_COLS, DEFAULT_NUM_COLS);
+            let text = read_text(sub_matches)?;
+            let outline_font = sub_matches.outline_font();
+            // outline fonts are only drawn through a renderer
+            let renderer = match (sub_matches.value_of("RENDERER"), &outline_font) {
+                (Some(name), _) => Some(name),
+                (None, Some(_)) => Some("braille"),
+                (None, None) => None,
+            };
+            let renderer = match renderer {
+                Some(name) => {
+                    let is_color = sub_matches.is_present("COLOR");
+                    let aspect_ratio =
+                        SimpleAspectRatio::new_auto_height(num_cols, TermFit::None, false);
+                    Some(get_renderer_impl(
+                        name,
+                        num_cols,
+                        is_color,
+                        false,
+                        aspect_ratio,
+                    )?)
+                }
+                None => None,
+            };
+            match (outline_font, renderer) {
+                (Some(data), Some(renderer)) => {
+                    let image = get_ttf_impl(&data, &text, sub_matches)?.rasterize();
+                    write_still(
+                        |writer| renderer.new_unicode_art(&image)?.write_all(writer),
+                        sub_matches,
+                    )
+                }
+                (_, renderer) => {
+                    let option = get_text_impl(&text, num_cols, sub_matches)?;
+                    write_still(
+                        |writer| draw_pattern(&option, renderer.as_deref(), num_cols, writer),
+                        sub_matches,
+                    )
+                }
+            }
         }
         Some(("braille", sub_matches)) => {
             let num_cols = sub_matches.num_cols(MIN_NUM_COLS, DEFAULT_NUM_COLS);
             let threshold = sub_matches.threshold();
             let is_color = sub_matches.is_present("COLOR");
             let is_invert = sub_matches.is_present("INVERT");
-            let is_stdin = sub_matches.is_present("STDIN");
-
-            let image = if is_stdin {
-                Reader::new(Input::stdin(stdin()))
-                    .with_guessed_format()?
-                    .decode()?
-            } else {
-                let image_path = sub_matches
-                    .value_of("IMAGE_PATH")
-                    .expect("Missing image path");
-                Reader::open(image_path)?.decode()?
-            };
-
-            BrailleAsciiArtOption::new(num_cols, threshold, is_color, is_invert)
-                .new_unicode_art(&image)?
-                .write_all(&mut buf)?;
-            Ok(())
+            let aspect_ratio = sub_matches.aspect_ratio(num_cols);
+            let option = BrailleAsciiArtOption::new(num_cols, threshold, is_color, is_invert)
+                .with_aspect_ratio(aspect_ratio);
+            run(&option, sub_matches)
         }
         Some(("subpixel", sub_matches)) => {
             let num_cols = sub_matches.num_cols(MIN_NUM_COLS, DEFAULT_NUM_COLS);
             let is_invert = sub_matches.is_present("INVERT");
-            let is_stdin = sub_matches.is_present("STDIN");
-
-            let image = if is_stdin {
-                Reader::new(Input::stdin(stdin()))
-                    .with_guessed_format()?
-                    .decode()?
-            } else {
-                let image_path = sub_matches
-                    .value_of("IMAGE_PATH")
-                    .expect("Missing image path");
-                Reader::open(image_path)?.decode()?
+            let aspect_ratio = sub_matches.aspect_ratio(num_cols);
+            let option =
+                SubpixelUnicodeArtOption::new(num_cols, is_invert).with_aspect_ratio(aspect_ratio);
+            run(&option, sub_matches)
+        }
+        Some(("emoji", sub_matches)) => {
+            let to_file = sub_matches.is_present("OUTPUT") || sub_matches.is_present("OUTPUT_DIR");
+            let format = match to_file {
+                true => sub_matches.output_format(OutputFormat::Text),
+                false => sub_matches.output_format(OutputFormat::Ansi),
             };
-
-            SubpixelUnicodeArtOption::new(num_cols, is_invert)
-                .new_unicode_art(&image)?
-                .write_all(&mut buf)?;
-            Ok(())
+            // neither CP437 nor the bitmap font of the raster have emoji
+            if let OutputFormat::AnsiArt | OutputFormat::Png = format {
+                eprintln!("Emoji can't be written to .{} files", format.extension());
+                exit(exitcode::USAGE)
+            }
+            let num_cols = sub_matches.num_cols(MIN_NUM_COLS, DEFAULT_NUM_COLS);
+            let aspect_ratio = sub_matches.aspect_ratio(num_cols);
+            let option = EmojiUnicodeArtOption::new(num_cols).with_aspect_ratio(aspect_ratio);
+            run(&option, sub_matches)
         }
+        Some(("ansi2img", sub_matches)) => reverse_art(sub_matches),
+        Some(("compare", sub_matches)) => compare_modes(sub_matches),
         _ => {
             unreachable!();
         }
@@ -0,0 +1,55 @@
+use image::{DynamicImage, Rgba};
+
+/// Pixels with an alpha value below this threshold are treated as transparent.
+pub const ALPHA_THRESHOLD: u8 = 128;
+
+pub trait Transparent {
+    fn is_transparent(&self) -> bool;
+}
+
+impl Transparent for Rgba<u8> {
+    #[inline]
+    fn is_transparent(&self) -> bool {
+        self[3] < ALPHA_THRESHOLD
+    }
+}
+
+pub trait Composite {
+    /// Blend every pixel over `background`, producing a fully opaque image.
+    fn composite(&self, background: Rgba<u8>) -> DynamicImage;
+}
+
+impl Composite for DynamicImage {
+    fn composite(&self, background: Rgba<u8>) -> DynamicImage {
+        let mut img = self.to_rgba8();
+        for pixel in img.pixels_mut() {
+            let alpha = pixel[3] as u32;
+            for c in 0..3 {
+                pixel[c] =
+                    ((pixel[c] as u32 * alpha + background[c] as u32 * (255 - alpha)) / 255) as u8;
+            }
+            pixel[3] = 255;
+        }
+        DynamicImage::ImageRgba8(img)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, RgbaImage};
+
+    #[test]
+    fn test_composite() {
+        let mut img = RgbaImage::new(3, 1);
+        img.put_pixel(0, 0, Rgba([10, 20, 30, 255]));
+        img.put_pixel(1, 0, Rgba([200, 100, 50, 0]));
+        img.put_pixel(2, 0, Rgba([255, 255, 255, 128]));
+        let img = DynamicImage::ImageRgba8(img).composite(Rgba([0, 0, 255, 255]));
+
+        assert_eq!(img.get_pixel(0, 0), Rgba([10, 20, 30, 255]));
+        assert_eq!(img.get_pixel(1, 0), Rgba([0, 0, 255, 255]));
+        assert_eq!(img.get_pixel(2, 0), Rgba([128, 128, 255, 255]));
+        assert!(!img.get_pixel(1, 0).is_transparent());
+    }
+}
@@ -0,0 +1,378 @@
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::io::Reader;
+use image::{AnimationDecoder, DynamicImage, ImageFormat};
+
+use super::delta::DeltaEncoder;
+use super::error::UnicodeArtError;
+use super::{PatternOption, UnicodeArtOption};
+
+/// Clear the whole screen
+pub const ANSI_CLEAR_SCREEN: &str = "\x1B[2J";
+
+// browsers treat tiny GIF delays as "as fast as possible" and slow them down
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+// the loop count is stored ahead of the image data
+const LOOP_SCAN_LEN: u64 = 64 * 1024;
+
+pub struct Frame<T> {
+    pub content: T,
+    pub delay: Duration,
+}
+
+impl<T> Frame<T> {
+    pub fn new(content: T, delay: Duration) -> Self {
+        Self { content, delay }
+    }
+
+    pub fn map<U, F>(self, f: F) -> Result<Frame<U>, UnicodeArtError>
+    where
+        F: FnOnce(T) -> Result<U, UnicodeArtError>,
+    {
+        Ok(Frame {
+            content: f(self.content)?,
+            delay: self.delay,
+        })
+    }
+}
+
+/// How often an animation asks to be played, 0 for forever, if the start
+/// of the file says so.
+fn stored_loop_count(head: &[u8], format: ImageFormat) -> Option<u32> {
+    let after = |tag: &[u8]| {
+        head.windows(tag.len())
+            .position(|window| window == tag)
+            .map(|i| &head[i + tag.len()..])
+    };
+    match format {
+        // the NETSCAPE2.0 extension counts the repeats after the first play
+        ImageFormat::Gif => match after(b"NETSCAPE2.0")? {
+            [3, 1, lo, hi, ..] => match u16::from_le_bytes([*lo, *hi]) {
+                0 => Some(0),
+                repeats => Some(repeats as u32 + 1),
+            },
+            _ => None,
+        },
+        // acTL: frame count, then play count
+        ImageFormat::Png => match after(b"acTL")? {
+            [_, _, _, _, a, b, c, d, ..] => Some(u32::from_be_bytes([*a, *b, *c, *d])),
+            _ => None,
+        },
+        // ANIM: chunk size, background color, then loop count
+        ImageFormat::WebP => match after(b"ANIM")? {
+            [_, _, _, _, _, _, _, _, lo, hi, ..] => Some(u16::from_le_bytes([*lo, *hi]) as u32),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Decode every frame of an animated GIF, APNG or WebP together with its
+/// delay, and the loop count stored in the file. Still images yield a single
+/// frame.
+pub fn decode_frames<R: BufRead + Seek>(
+    mut reader: R,
+) -> Result<(Vec<Frame<DynamicImage>>, Option<u32>), UnicodeArtError> {
+    let start = reader.stream_position()?;
+    let mut head = Vec::new();
+    reader.by_ref().take(LOOP_SCAN_LEN).read_to_end(&mut head)?;
+    reader.seek(SeekFrom::Start(start))?;
+
+    let reader = Reader::new(reader).with_guessed_format()?;
+    let loop_count = reader
+        .format()
+        .and_then(|format| stored_loop_count(&head, format));
+    let still = |image| Ok((vec![Frame::new(image, Duration::ZERO)], None));
+    let frames = match reader.format() {
+        Some(ImageFormat::Gif) => GifDecoder::new(reader.into_inner())?
+            .into_frames()
+            .collect_frames()?,
+        Some(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(reader.into_inner())?;
+            if !decoder.is_apng() {
+                return still(DynamicImage::from_decoder(decoder)?);
+            }
+            decoder.apng().into_frames().collect_frames()?
+        }
+        Some(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(reader.into_inner())?;
+            if !decoder.has_animation() {
+                return still(DynamicImage::from_decoder(decoder)?);
+            }
+            decoder.into_frames().collect_frames()?
+        }
+        _ => return still(reader.decode()?),
+    };
+    let frames = frames
+        .into_iter()
+        .map(|frame| {
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            let delay = Duration::from_micros(numer as u64 * 1000 / denom.max(1) as u64);
+            let delay = match delay < MIN_FRAME_DELAY {
+                true => DEFAULT_FRAME_DELAY,
+                false => delay,
+            };
+            Frame::new(DynamicImage::ImageRgba8(frame.into_buffer()), delay)
+        })
+        .collect();
+    Ok((frames, loop_count))
+}
+
+/// Render every frame with the same renderer options.
+pub fn render_frames(
+    option: &dyn UnicodeArtOption,
+    frames: Vec<Frame<DynamicImage>>,
+) -> Result<Vec<Frame<String>>, UnicodeArtError> {
+    frames
+        .into_iter()
+        .map(|frame| {
+            frame.map(|image| {
+                let mut buf = Vec::new();
+                option.new_unicode_art(&image)?.write_all(&mut buf)?;
+                Ok(String::from_utf8_lossy(&buf).into_owned())
+            })
+        })
+        .collect()
+}
+
+/// Generated patterns that change over time.
+pub trait AnimatedOption: PatternOption {
+    /// Settings of the pattern `t` seconds into the animation.
+    fn at_time(&self, t: f64) -> Box<dyn PatternOption>;
+}
+
+/// Draw `fps` frames a second of a pattern, a single still frame when
+/// `duration` is zero. `draw` writes the pattern as it is `t` seconds in.
+pub fn animate<F>(
+    fps: f64,
+    duration: Duration,
+    draw: F,
+) -> Result<Vec<Frame<String>>, UnicodeArtError>
+where
+    F: Fn(f64, &mut dyn Write) -> Result<(), UnicodeArtError>,
+{
+    let count = ((duration.as_secs_f64() * fps).round() as usize).max(1);
+    let delay = match duration.is_zero() {
+        true => Duration::ZERO,
+        false => Duration::from_secs_f64(1.0 / fps),
+    };
+    (0..count)
+        .map(|index| {
+            let mut buf = Vec::new();
+            draw(index as f64 / fps, &mut buf)?;
+            Ok(Frame::new(
+                String::from_utf8_lossy(&buf).into_owned(),
+                delay,
+            ))
+        })
+        .collect()
+}
+
+/// Zero based frame indices, written as a comma separated list of indices and
+/// ranges such as `0-4,8,10-`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameSelection {
+    ranges: Vec<(usize, Option<usize>)>,
+}
+
+impl FrameSelection {
+    pub fn contains(&self, index: usize) -> bool {
+        self.ranges
+            .iter()
+            .any(|&(start, end)| index >= start && end.is_none_or(|end| index <= end))
+    }
+
+    pub fn select<T>(&self, frames: Vec<Frame<T>>) -> Vec<Frame<T>> {
+        frames
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| self.contains(*index))
+            .map(|(_, frame)| frame)
+            .collect()
+    }
+}
+
+impl FromStr for FrameSelection {
+    type Err = UnicodeArtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |v: &str| {
+            v.trim()
+                .parse::<usize>()
+                .map_err(|_| UnicodeArtError::UnsupportError)
+        };
+        let ranges = s
+            .split(',')
+            .map(|part| match part.split_once('-') {
+                Some((start, "")) => Ok((parse(start)?, None)),
+                Some((start, end)) => Ok((parse(start)?, Some(parse(end)?))),
+                None => parse(part).map(|index| (index, Some(index))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(FrameSelection { ranges })
+    }
+}
+
+/// Plays rendered frames in place, redrawing only the cells that changed
+/// since the previous frame.
+pub struct Player {
+    // 0 loops forever
+    loop_count: u32,
+}
+
+impl Player {
+    pub fn new(loop_count: u32) -> Self {
+        Self { loop_count }
+    }
+
+    pub fn play(
+        &self,
+        frames: &[Frame<String>],
+        writer: &mut dyn Write,
+    ) -> Result<(), UnicodeArtError> {
+        let mut encoder = DeltaEncoder::default();
+        let mut round = 0;
+        while self.loop_count == 0 || round < self.loop_count {
+            let mut deadline = Instant::now();
+            for frame in frames {
+                write!(writer, "{}", encoder.encode(&frame.content))?;
+                writer.flush()?;
+                // keep the pace even when rendering or writing was slow
+                deadline += frame.delay;
+                let now = Instant::now();
+                if deadline > now {
+                    thread::sleep(deadline - now);
+                }
+            }
+            round += 1;
+        }
+        Ok(())
+    }
+
+    /// Non-interactive mode: write all frames one after another.
+    pub fn dump(frames: &[Frame<String>], writer: &mut dyn Write) -> Result<(), UnicodeArtError> {
+        for (index, frame) in frames.iter().enumerate() {
+            if index > 0 {
+                writeln!(writer)?;
+            }
+            write!(writer, "{}", frame.content)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unicode_art::aspect_ratio::{SimpleAspectRatio, TermFit};
+    use crate::unicode_art::classic::ClassicAsciiArtOption;
+    use crate::unicode_art::procedural::{Procedural, ProceduralAsciiArtOption};
+    use image::codecs::gif::{GifEncoder, Repeat};
+    use image::{Delay, Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    fn animated_gif(repeat: Option<Repeat>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            if let Some(repeat) = repeat {
+                encoder.set_repeat(repeat).unwrap();
+            }
+            for (value, delay) in [(0u8, 50u32), (255, 5), (0, 200)] {
+                let image = RgbaImage::from_pixel(4, 4, Rgba([value, value, value, 255]));
+                let frame =
+                    image::Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(delay, 1));
+                encoder.encode_frame(frame).unwrap();
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_decode_frames() -> Result<(), UnicodeArtError> {
+        let (frames, loop_count) = decode_frames(Cursor::new(animated_gif(None)))?;
+        let delays: Vec<_> = frames.iter().map(|f| f.delay.as_millis()).collect();
+        // GIF delays are stored in centiseconds, tiny delays are slowed down
+        assert_eq!(vec![50, 100, 200], delays);
+        assert_eq!(None, loop_count);
+
+        let (still, loop_count) =
+            decode_frames(Cursor::new(std::fs::read("tests/support/test_gundam.png")?))?;
+        assert_eq!(1, still.len());
+        assert_eq!(None, loop_count);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stored_loop_count() -> Result<(), UnicodeArtError> {
+        let gif = animated_gif(Some(Repeat::Infinite));
+        assert_eq!(Some(0), decode_frames(Cursor::new(gif))?.1);
+        // GIF counts the repeats after the first play
+        let gif = animated_gif(Some(Repeat::Finite(2)));
+        assert_eq!(Some(3), decode_frames(Cursor::new(gif))?.1);
+
+        let actl = [b"acTL".as_slice(), &[0, 0, 0, 3, 0, 0, 0, 5]].concat();
+        assert_eq!(Some(5), stored_loop_count(&actl, ImageFormat::Png));
+        let anim = [b"ANIM".as_slice(), &[6, 0, 0, 0, 0, 0, 0, 0, 4, 0]].concat();
+        assert_eq!(Some(4), stored_loop_count(&anim, ImageFormat::WebP));
+        assert_eq!(None, stored_loop_count(b"acTL", ImageFormat::Png));
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_frames() -> Result<(), UnicodeArtError> {
+        let (frames, _) = decode_frames(Cursor::new(animated_gif(None)))?;
+        let option = ClassicAsciiArtOption::new_level_10(2, false, false);
+        let frames = render_frames(&option, frames)?;
+        let selection: FrameSelection = "0-1".parse()?;
+        let frames = selection.select(frames);
+
+        let mut buf = Vec::new();
+        Player::dump(&frames, &mut buf)?;
+        assert_eq!("@@\n\n  \n", String::from_utf8(buf).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_animate() -> Result<(), UnicodeArtError> {
+        let aspect_ratio = SimpleAspectRatio::new(Some(9), Some(3), TermFit::None, false);
+        let option = ProceduralAsciiArtOption::new(Procedural::Automaton(90), 9)
+            .with_aspect_ratio(aspect_ratio)
+            .with_charset(" #");
+        let draw =
+            |t: f64, writer: &mut dyn Write| option.at_time(t).new_pattern_art()?.write_all(writer);
+        let frames = animate(10.0, Duration::from_millis(300), draw)?;
+        let contents: Vec<_> = frames.iter().map(|f| f.content.as_str()).collect();
+        // the automaton scrolls up a generation every frame
+        assert_eq!(
+            vec![
+                "    #    \n   # #   \n  #   #  \n",
+                "   # #   \n  #   #  \n # # # # \n",
+                "  #   #  \n # # # # \n#       #\n",
+            ],
+            contents
+        );
+        assert!(frames.iter().all(|f| f.delay == Duration::from_millis(100)));
+
+        let still = animate(10.0, Duration::ZERO, draw)?;
+        assert_eq!(1, still.len());
+        assert_eq!(Duration::ZERO, still[0].delay);
+        Ok(())
+    }
+
+    #[test]
+    fn test_frame_selection() -> Result<(), UnicodeArtError> {
+        let selection: FrameSelection = "1-3,5,8-".parse()?;
+        let selected: Vec<_> = (0..10).filter(|&i| selection.contains(i)).collect();
+        assert_eq!(vec![1, 2, 3, 5, 8, 9], selected);
+        assert!("1-x".parse::<FrameSelection>().is_err());
+        Ok(())
+    }
+}
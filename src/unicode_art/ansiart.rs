@@ -0,0 +1,308 @@
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::Rgb;
+
+use super::color::VGA_PALETTE;
+use super::error::UnicodeArtError;
+use super::export::Metadata;
+use super::grid::{Cell, CellGrid};
+
+/// Lines this wide wrap on their own in ANSI art viewers, without CR LF.
+pub const ANSI_ART_WIDTH: usize = 80;
+pub const DEFAULT_SAUCE_FONT: &str = "IBM VGA";
+
+/// Ends the art, viewers stop reading before the SAUCE record
+pub(super) const EOF: u8 = 0x1A;
+const SAUCE_LEN: usize = 128;
+const DEFAULT_FG: u8 = 7;
+const DEFAULT_BG: u8 = 0;
+
+/// Closest of the 16 VGA colours.
+pub fn nearest_vga(color: Rgb<u8>) -> u8 {
+    (0..16)
+        .min_by_key(|&i| {
+            VGA_PALETTE[i as usize]
+                .iter()
+                .zip(color.0)
+                .map(|(&p, c)| (p as i32 - c as i32).pow(2))
+                .sum::<i32>()
+        })
+        .unwrap()
+}
+
+// the upper half of code page 437
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»\
+                          ░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀\
+                          αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{A0}";
+
+/// The character of a code page 437 byte, control codes are kept as they are.
+pub fn from_cp437(byte: u8) -> char {
+    match byte {
+        0x00..=0x7F => byte as char,
+        _ => CP437_HIGH.chars().nth(byte as usize - 0x80).unwrap(),
+    }
+}
+
+/// The code page 437 byte for a character, approximating characters that
+/// don't exist in CP437 such as braille patterns and quadrants.
+pub fn to_cp437(ch: char) -> u8 {
+    match ch {
+        ' '..='~' => ch as u8,
+        '░' => 0xB0,
+        '▒' => 0xB1,
+        '▓' => 0xB2,
+        '█' => 0xDB,
+        '▄' | '▖' | '▗' => 0xDC,
+        '▌' => 0xDD,
+        '▐' => 0xDE,
+        '▀' | '▘' | '▝' => 0xDF,
+        '▙' | '▚' | '▛' | '▜' | '▞' | '▟' => 0xB2,
+        '·' => 0xFA,
+        '■' => 0xFE,
+        '\u{2800}'..='\u{28FF}' => match (ch as u32 - 0x2800).count_ones() {
+            0 => b' ',
+            1..=2 => 0xB0,
+            3..=5 => 0xB1,
+            6..=7 => 0xB2,
+            _ => 0xDB,
+        },
+        _ => CP437_HIGH
+            .chars()
+            .position(|c| c == ch)
+            .map_or(b'?', |i| 0x80 + i as u8),
+    }
+}
+
+fn colors(cell: &Cell) -> (u8, u8) {
+    (
+        cell.fg.map_or(DEFAULT_FG, nearest_vga),
+        cell.bg.map_or(DEFAULT_BG, nearest_vga),
+    )
+}
+
+/// SGR switching between 16-colour states. Bright foregrounds use bold and
+/// bright backgrounds use blink, which iCE colour viewers show as bright.
+fn sgr(from: Option<(u8, u8)>, (fg, bg): (u8, u8)) -> Vec<u8> {
+    let mut params = Vec::new();
+    let from = match from {
+        // bold and blink can only be switched off with a reset
+        Some((from_fg, from_bg)) if (from_fg < 8 || fg >= 8) && (from_bg < 8 || bg >= 8) => {
+            (from_fg, from_bg)
+        }
+        _ => {
+            params.push(0);
+            (DEFAULT_FG, DEFAULT_BG)
+        }
+    };
+    if fg >= 8 && from.0 < 8 {
+        params.push(1);
+    }
+    if bg >= 8 && from.1 < 8 {
+        params.push(5);
+    }
+    if fg % 8 != from.0 % 8 || params.first() == Some(&0) {
+        params.push(30 + fg % 8);
+    }
+    if bg % 8 != from.1 % 8 || params.first() == Some(&0) {
+        params.push(40 + bg % 8);
+    }
+    let params: Vec<_> = params.iter().map(u8::to_string).collect();
+    format!("\x1B[{}m", params.join(";")).into_bytes()
+}
+
+/// Days since the epoch to (year, month, day), after Howard Hinnant's
+/// `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!("{:04}{:02}{:02}", year, month, day)
+}
+
+fn field(record: &mut Vec<u8>, value: &str, len: usize, pad: u8) {
+    let mut bytes: Vec<u8> = value.chars().map(to_cp437).take(len).collect();
+    bytes.resize(len, pad);
+    record.extend(bytes);
+}
+
+/// The 128 byte SAUCE record describing an ANSi file.
+fn sauce_record(
+    metadata: &Metadata,
+    date: &str,
+    file_size: usize,
+    width: usize,
+    lines: usize,
+) -> Vec<u8> {
+    let mut record = Vec::with_capacity(SAUCE_LEN);
+    record.extend(b"SAUCE00");
+    field(
+        &mut record,
+        metadata.title.as_deref().unwrap_or(""),
+        35,
+        b' ',
+    );
+    field(
+        &mut record,
+        metadata.author.as_deref().unwrap_or(""),
+        20,
+        b' ',
+    );
+    field(
+        &mut record,
+        metadata.group.as_deref().unwrap_or(""),
+        20,
+        b' ',
+    );
+    field(&mut record, date, 8, b' ');
+    record.extend((file_size as u32).to_le_bytes());
+    // data type character, file type ANSi
+    record.extend([1, 1]);
+    record.extend((width as u16).to_le_bytes());
+    record.extend((lines as u16).to_le_bytes());
+    record.extend([0; 4]);
+    // no comments
+    record.push(0);
+    // iCE colours, 8 pixel letter spacing
+    record.push(0b0000_0011);
+    let font = metadata.font.as_deref().unwrap_or(DEFAULT_SAUCE_FONT);
+    field(&mut record, font, 22, 0);
+    record
+}
+
+/// Write cells as a classic ANSI art file: CP437 characters, 16-colour SGR
+/// with iCE colours, CR LF line ends except on lines that wrap at 80
+/// columns, an EOF marker and a SAUCE record.
+pub fn write_ansi_art(
+    grid: &CellGrid,
+    metadata: &Metadata,
+    writer: &mut dyn Write,
+) -> Result<(), UnicodeArtError> {
+    write_ansi_art_dated(grid, metadata, &today(), writer)
+}
+
+fn write_ansi_art_dated(
+    grid: &CellGrid,
+    metadata: &Metadata,
+    date: &str,
+    writer: &mut dyn Write,
+) -> Result<(), UnicodeArtError> {
+    let mut data = Vec::new();
+    let mut state = None;
+    for cells in grid.rows().take(grid.height()) {
+        for cell in cells {
+            // CP437 has no wide characters, the right half stays blank
+            let ch = match cell.is_wide_tail() {
+                true => b' ',
+                false => to_cp437(cell.ch),
+            };
+            let (fg, bg) = colors(cell);
+            // the foreground of a blank doesn't show, keep the current one
+            let target = match (ch, state) {
+                (b' ', Some((current_fg, _))) => (current_fg, bg),
+                _ => (fg, bg),
+            };
+            if state != Some(target) {
+                data.extend(sgr(state, target));
+                state = Some(target);
+            }
+            data.push(ch);
+        }
+        if grid.width() != ANSI_ART_WIDTH {
+            data.extend(b"\r\n");
+        }
+    }
+    data.extend(b"\x1B[0m");
+    let sauce = sauce_record(metadata, date, data.len(), grid.width(), grid.height());
+    writer.write_all(&data)?;
+    writer.write_all(&[EOF])?;
+    writer.write_all(&sauce)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_cp437() {
+        assert_eq!(b'A', to_cp437('A'));
+        assert_eq!(0xDF, to_cp437('▀'));
+        assert_eq!(0xDC, to_cp437('▄'));
+        assert_eq!(b' ', to_cp437('\u{2800}'));
+        assert_eq!(0xDB, to_cp437('\u{28FF}'));
+        assert_eq!(0x82, to_cp437('é'));
+        assert_eq!(b'?', to_cp437('€'));
+        assert!((0x80..=0xFF).all(|b| to_cp437(from_cp437(b)) == b));
+    }
+
+    #[test]
+    fn test_sgr() {
+        assert_eq!(b"\x1B[0;37;40m".to_vec(), sgr(None, (7, 0)));
+        assert_eq!(b"\x1B[1;5;31;44m".to_vec(), sgr(Some((7, 0)), (9, 12)));
+        assert_eq!(b"\x1B[32m".to_vec(), sgr(Some((9, 12)), (10, 12)));
+        // back from bright needs a reset
+        assert_eq!(b"\x1B[0;5;32;44m".to_vec(), sgr(Some((10, 12)), (2, 12)));
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!((1970, 1, 1), civil_from_days(0));
+        assert_eq!((2000, 2, 29), civil_from_days(11_016));
+        assert_eq!((2026, 10, 18), civil_from_days(20_744));
+    }
+
+    #[test]
+    fn test_write_ansi_art() -> Result<(), UnicodeArtError> {
+        let grid = CellGrid::parse("\x1B[38;2;255;255;255m\x1B[48;2;170;0;0m▀\x1B[0m \n");
+        let metadata = Metadata {
+            title: Some("Gundam".to_string()),
+            author: Some("team".to_string()),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        write_ansi_art_dated(&grid, &metadata, "20261018", &mut buf)?;
+        let data = b"\x1B[0;1;37;41m\xDF\x1B[40m \r\n\x1B[0m";
+        assert_eq!(&data[..], &buf[..data.len()]);
+        assert_eq!(EOF, buf[data.len()]);
+
+        let sauce = &buf[data.len() + 1..];
+        assert_eq!(SAUCE_LEN, sauce.len());
+        assert_eq!(b"SAUCE00Gundam ", &sauce[..14]);
+        assert_eq!(b"team ", &sauce[42..47]);
+        assert_eq!(b"20261018", &sauce[82..90]);
+        assert_eq!(
+            data.len() as u32,
+            u32::from_le_bytes(sauce[90..94].try_into().unwrap())
+        );
+        // character / ANSi, 2 columns, 1 line
+        assert_eq!([1, 1, 2, 0, 1, 0], sauce[94..100]);
+        assert_eq!(0b11, sauce[105]);
+        assert_eq!(b"IBM VGA\0", &sauce[106..114]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrap_at_80_columns() -> Result<(), UnicodeArtError> {
+        let line = "x".repeat(ANSI_ART_WIDTH);
+        let grid = CellGrid::parse(&format!("{}\n{}\n", line, line));
+        let mut buf = Vec::new();
+        write_ansi_art_dated(&grid, &Metadata::default(), "20261018", &mut buf)?;
+        assert!(!buf.windows(2).any(|w| w == b"\r\n"));
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use std::io::Write;
+use std::time::Duration;
+
+use super::animation::Frame;
+use super::delta::DeltaEncoder;
+use super::error::UnicodeArtError;
+use super::grid::CellGrid;
+
+/// Escape a string for a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 || c == '\x7F' => {
+                escaped.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Write rendered frames as an asciinema v2 recording: a JSON header line
+/// followed by one `[time, "o", data]` output event per frame. Frames are
+/// delta encoded, so the recording only needs cursor positioning and never
+/// relies on newline translation by the player.
+pub fn write_asciicast(
+    frames: &[Frame<String>],
+    title: Option<&str>,
+    writer: &mut dyn Write,
+) -> Result<(), UnicodeArtError> {
+    let (width, height) = frames
+        .iter()
+        .map(|frame| CellGrid::parse(&frame.content))
+        .fold((1, 1), |(width, height), grid| {
+            // one more row for the parked cursor
+            (width.max(grid.width()), height.max(grid.height() + 1))
+        });
+    let title = match title {
+        Some(title) => format!(", \"title\": {}", json_string(title)),
+        None => String::new(),
+    };
+    writeln!(
+        writer,
+        "{{\"version\": 2, \"width\": {}, \"height\": {}{}}}",
+        width, height, title
+    )?;
+    let mut encoder = DeltaEncoder::default();
+    let mut time = Duration::ZERO;
+    for frame in frames {
+        let data = encoder.encode(&frame.content);
+        writeln!(
+            writer,
+            "[{:.6}, \"o\", {}]",
+            time.as_secs_f64(),
+            json_string(&data)
+        )?;
+        time += frame.delay;
+    }
+    // hold the last frame for its whole delay
+    if time > Duration::ZERO {
+        writeln!(writer, "[{:.6}, \"o\", \"\"]", time.as_secs_f64())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(r#""a\"b\\c\n""#, json_string("a\"b\\c\n"));
+        assert_eq!(r#""\u001b[0m""#, json_string("\x1B[0m"));
+    }
+
+    #[test]
+    fn test_write_asciicast() -> Result<(), UnicodeArtError> {
+        let frames = vec![
+            Frame::new("ab\ncd\n".to_string(), Duration::from_millis(100)),
+            Frame::new("ax\ncd\n".to_string(), Duration::from_millis(250)),
+        ];
+        let mut buf = Vec::new();
+        write_asciicast(&frames, None, &mut buf)?;
+        assert_eq!(
+            r#"{"version": 2, "width": 2, "height": 3}
+[0.000000, "o", "\u001b[2J\u001b[1;1Hab\u001b[2;1Hcd\u001b[3;1H"]
+[0.100000, "o", "\u001b[1;2Hx\u001b[3;1H"]
+[0.350000, "o", ""]
+"#,
+            String::from_utf8(buf).unwrap()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_wide_chars() -> Result<(), UnicodeArtError> {
+        let frames = vec![
+            Frame::new("🟥🟦\n".to_string(), Duration::from_millis(100)),
+            Frame::new("🟥⬜\n".to_string(), Duration::from_millis(100)),
+        ];
+        let mut buf = Vec::new();
+        write_asciicast(&frames, None, &mut buf)?;
+        assert_eq!(
+            r#"{"version": 2, "width": 4, "height": 2}
+[0.000000, "o", "\u001b[2J\u001b[1;1H🟥🟦\u001b[2;1H"]
+[0.100000, "o", "\u001b[1;3H⬜\u001b[2;1H"]
+[0.200000, "o", ""]
+"#,
+            String::from_utf8(buf).unwrap()
+        );
+        Ok(())
+    }
+}
@@ -1,40 +1,88 @@
+use std::str::FromStr;
+
+use super::error::UnicodeArtError;
+use super::term::terminal_size;
+
+/// Width / height of a terminal cell. Most monospace fonts draw cells about
+/// twice as tall as they are wide.
+pub const DEFAULT_CELL_ASPECT: f64 = 0.5;
+
 pub trait AspectRatio {
     fn calculate(&self, img_width: u32, img_height: u32) -> (u32, u32);
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub enum TermFit {
+    /// Ignore the terminal entirely
+    None,
+    /// Use the requested size, but never overflow the terminal width, the
+    /// default of `--fit`
+    #[default]
     Auto,
-    // Fit,
-    // Width,
-    // Height,
+    /// Largest size that fits both the terminal width and height
+    Fit,
+    /// Fill the terminal width
+    Width,
+    /// Fill the terminal height
+    Height,
 }
 
-impl Default for TermFit {
-    fn default() -> Self {
-        TermFit::Auto
+impl FromStr for TermFit {
+    type Err = UnicodeArtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(TermFit::None),
+            "auto" => Ok(TermFit::Auto),
+            "fit" => Ok(TermFit::Fit),
+            "width" => Ok(TermFit::Width),
+            "height" => Ok(TermFit::Height),
+            _ => Err(UnicodeArtError::UnsupportError),
+        }
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Copy)]
 pub struct SimpleAspectRatio {
     expect_width: Option<u32>,
     expect_height: Option<u32>,
     termfit: TermFit,
     use_border: bool,
+    cell_aspect: f64,
+    /// Terminal columns every cell of the art takes, 2 for wide characters
+    /// like emoji
+    cell_columns: u32,
+    // overrides the detected terminal size
+    term_size: Option<(u32, u32)>,
+}
+
+// the library never looks at the terminal unless a fit is asked for, so its
+// output doesn't depend on where it runs
+impl Default for SimpleAspectRatio {
+    fn default() -> Self {
+        Self::new(None, None, TermFit::None, false)
+    }
 }
 
 impl SimpleAspectRatio {
-    // the 2.0f and 0.5f factors are used for text displays that (usually) have characters
+    // the cell aspect compensates for text displays that (usually) have characters
     // that are taller than they are wide.
     #[inline]
     fn calc_width(&self, height: u32, img_width: u32, img_height: u32) -> u32 {
-        (2.0 * height as f64 * img_width as f64 / img_height as f64).round() as u32
+        (height as f64 * img_width as f64 / img_height as f64 / self.wide_cell_aspect()).round()
+            as u32
     }
 
     #[inline]
     fn calc_height(&self, width: u32, img_width: u32, img_height: u32) -> u32 {
-        (0.5 * width as f64 * img_height as f64 / img_width as f64).round() as u32
+        (self.wide_cell_aspect() * width as f64 * img_height as f64 / img_width as f64).round()
+            as u32
+    }
+
+    /// Width / height of a cell of the art, which may span several columns.
+    #[inline]
+    fn wide_cell_aspect(&self) -> f64 {
+        self.cell_aspect * self.cell_columns as f64
     }
 
     #[inline]
@@ -55,61 +103,191 @@ impl SimpleAspectRatio {
         // adjust for too small dimensions
         let mut height = height;
         while width == 0 {
-            height = height + 1;
-            width = self.calc_height(height, img_width, img_height);
+            height += 1;
+            width = self.calc_width(height, img_width, img_height);
         }
+        (width, height)
+    }
 
-        let term_width = 80;
-        while matches!(self.termfit, TermFit::Auto)
-            && (width + self.use_border as u32 * 2) > term_width
-        {
-            width = term_width - self.use_border as u32 * 2;
-            (width, height) = self.auto_height(width, img_width, img_height);
+    /// Room left in the terminal for the art, keeping the last row for the
+    /// cursor so that the output never scrolls.
+    fn term_area(&self) -> Option<(u32, u32)> {
+        if self.termfit == TermFit::None {
+            return None;
         }
-        (width, height)
+        let border = self.use_border as u32 * 2;
+        self.term_size.or_else(terminal_size).map(|(cols, rows)| {
+            (
+                (cols.saturating_sub(border) / self.cell_columns).max(1),
+                rows.saturating_sub(border + 1).max(1),
+            )
+        })
     }
 
     pub fn new_auto_width(expect_height: u32, termfit: TermFit, use_border: bool) -> Self {
-        Self {
-            expect_width: None,
-            expect_height: Some(expect_height),
-            termfit,
-            use_border,
-        }
+        Self::new(None, Some(expect_height), termfit, use_border)
     }
 
     pub fn new_auto_height(expect_width: u32, termfit: TermFit, use_border: bool) -> Self {
+        Self::new(Some(expect_width), None, termfit, use_border)
+    }
+
+    /// When both dimensions are given the image is stretched to exactly that
+    /// many cells; when neither is given every pixel becomes a cell.
+    pub fn new(
+        expect_width: Option<u32>,
+        expect_height: Option<u32>,
+        termfit: TermFit,
+        use_border: bool,
+    ) -> Self {
         Self {
-            expect_width: Some(expect_width),
-            expect_height: None,
+            expect_width,
+            expect_height,
             termfit,
             use_border,
+            cell_aspect: DEFAULT_CELL_ASPECT,
+            cell_columns: 1,
+            term_size: None,
         }
     }
 
-    // pub fn new(
-    //     expect_width: Option<u32>,
-    //     expect_height: Option<u32>,
-    //     termfit: TermFit,
-    //     use_border: bool,
-    // ) -> Self {
-    //     Self {
-    //         expect_width,
-    //         expect_height,
-    //         termfit,
-    //         use_border,
-    //     }
-    // }
+    pub fn with_cell_aspect(mut self, cell_aspect: f64) -> Self {
+        self.cell_aspect = cell_aspect;
+        self
+    }
+
+    /// Measure widths in cells that are `cell_columns` terminal columns wide,
+    /// `--width` and the terminal width still count columns.
+    pub fn with_cell_columns(mut self, cell_columns: u32) -> Self {
+        self.cell_columns = cell_columns.max(1);
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_term_size(mut self, term_size: (u32, u32)) -> Self {
+        self.term_size = Some(term_size);
+        self
+    }
 }
 
 impl AspectRatio for SimpleAspectRatio {
     fn calculate(&self, img_width: u32, img_height: u32) -> (u32, u32) {
-        match (self.expect_width, self.expect_height) {
-            // auto width
-            (None, Some(height)) => self.auto_width(height, img_width, img_height),
-            // auto height
-            (Some(width), None) => self.auto_height(width, img_width, img_height),
-            _ => (img_width, img_height),
+        let area = self.term_area();
+        match (self.termfit, area) {
+            (TermFit::Fit, Some((max_width, max_height))) => {
+                let (width, height) = self.auto_height(max_width, img_width, img_height);
+                if height <= max_height {
+                    return (width, height);
+                }
+                let (width, height) = self.auto_width(max_height, img_width, img_height);
+                // clamping only the width would leave the height of the wider art
+                match width > max_width {
+                    true => self.auto_height(max_width, img_width, img_height),
+                    false => (width, height),
+                }
+            }
+            (TermFit::Width, Some((max_width, _))) => {
+                self.auto_height(max_width, img_width, img_height)
+            }
+            (TermFit::Height, Some((_, max_height))) => {
+                self.auto_width(max_height, img_width, img_height)
+            }
+            _ => {
+                let expect_width = self
+                    .expect_width
+                    .map(|width| (width / self.cell_columns).max(1));
+                let (width, height) = match (expect_width, self.expect_height) {
+                    // auto width
+                    (None, Some(height)) => self.auto_width(height, img_width, img_height),
+                    // auto height
+                    (Some(width), None) => self.auto_height(width, img_width, img_height),
+                    (Some(width), Some(height)) => (width, height),
+                    (None, None) => (img_width, img_height),
+                };
+                match area {
+                    Some((max_width, _)) if self.termfit == TermFit::Auto && width > max_width => {
+                        self.auto_height(max_width, img_width, img_height)
+                    }
+                    _ => (width, height),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_termfit() {
+        // a square image in an 80x25 terminal, cells twice as tall as wide
+        let calc = |termfit| {
+            SimpleAspectRatio::new_auto_height(100, termfit, false)
+                .with_term_size((80, 25))
+                .calculate(300, 300)
+        };
+        assert_eq!((100, 50), calc(TermFit::None));
+        assert_eq!((80, 40), calc(TermFit::Auto));
+        assert_eq!((48, 24), calc(TermFit::Fit));
+        assert_eq!((80, 40), calc(TermFit::Width));
+        assert_eq!((48, 24), calc(TermFit::Height));
+    }
+
+    #[test]
+    fn test_fit_keeps_aspect() {
+        for (img_width, img_height) in [(300, 300), (600, 100), (100, 600), (1000, 999)] {
+            for term_size in [(80, 25), (7, 3), (200, 10), (13, 60)] {
+                let fit = SimpleAspectRatio::new_auto_height(100, TermFit::Fit, false)
+                    .with_term_size(term_size);
+                let (width, height) = fit.calculate(img_width, img_height);
+                assert!(width <= term_size.0 && height < term_size.1);
+                let expected = fit.calc_height(width, img_width, img_height).max(1);
+                assert!(height.abs_diff(expected) <= 1, "{}x{}", width, height);
+            }
         }
     }
+
+    #[test]
+    fn test_border() {
+        let (width, height) = SimpleAspectRatio::new_auto_height(100, TermFit::Fit, true)
+            .with_term_size((80, 25))
+            .calculate(600, 100);
+        assert_eq!((78, 7), (width, height));
+    }
+
+    #[test]
+    fn test_cell_aspect() {
+        let calc = |cell_aspect| {
+            SimpleAspectRatio::new_auto_height(90, TermFit::None, false)
+                .with_cell_aspect(cell_aspect)
+                .calculate(300, 200)
+        };
+        assert_eq!((90, 30), calc(DEFAULT_CELL_ASPECT));
+        assert_eq!((90, 27), calc(0.45));
+        assert_eq!(
+            (120, 40),
+            SimpleAspectRatio::new_auto_width(40, TermFit::None, false).calculate(300, 200)
+        );
+    }
+
+    #[test]
+    fn test_cell_columns() {
+        // emoji are two columns wide and about square
+        let calc = |termfit| {
+            SimpleAspectRatio::new_auto_height(100, termfit, false)
+                .with_cell_columns(2)
+                .with_term_size((80, 25))
+                .calculate(300, 300)
+        };
+        assert_eq!((50, 50), calc(TermFit::None));
+        assert_eq!((40, 40), calc(TermFit::Auto));
+        assert_eq!((24, 24), calc(TermFit::Fit));
+        assert_eq!(
+            (40, 20),
+            SimpleAspectRatio::new_auto_width(20, TermFit::None, false)
+                .with_cell_columns(2)
+                .calculate(200, 100)
+        );
+    }
 }
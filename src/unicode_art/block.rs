@@ -1,4 +1,6 @@
-use super::color::{AnsiColor, ANSI_BG_COLOUR_ESCAPES, ANSI_RESET_ATTRIBUTES};
+use super::alpha::Transparent;
+use super::aspect_ratio::{AspectRatio, SimpleAspectRatio, TermFit};
+use super::color::{AnsiColor, ANSI_DEFAULT_BACKGROUND, ANSI_RESET_ATTRIBUTES};
 use super::error::UnicodeArtError;
 use super::{UnicodeArt, UnicodeArtOption};
 use image::{DynamicImage, GenericImageView};
@@ -7,12 +9,20 @@ use std::io::Write;
 #[derive(Default)]
 pub struct BlockUnicodeArtOption {
     is_color: bool,
-    num_cols: u32,
+    aspect_ratio: SimpleAspectRatio,
 }
 
 impl BlockUnicodeArtOption {
     pub fn new(num_cols: u32, is_color: bool) -> Self {
-        Self { num_cols, is_color }
+        Self {
+            aspect_ratio: SimpleAspectRatio::new_auto_height(num_cols, TermFit::None, false),
+            is_color,
+        }
+    }
+
+    pub fn with_aspect_ratio(mut self, aspect_ratio: SimpleAspectRatio) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
     }
 }
 
@@ -35,15 +45,15 @@ pub struct BlockUnicodeArt<'a> {
 
 impl<'a> UnicodeArt for BlockUnicodeArt<'a> {
     fn write_all(&self, writer: &mut dyn Write) -> Result<(), UnicodeArtError> {
-        let (width, height) = (self.image.width(), self.image.height());
-        let mut img = self.image.thumbnail(
-            self.options.num_cols,
-            ((self.options.num_cols as f64 / width as f64) * height as f64) as u32,
-        );
+        let (num_cols, num_rows) = self
+            .options
+            .aspect_ratio
+            .calculate(self.image.width(), self.image.height());
+        // every cell shows two pixels stacked vertically
+        let mut img = self.image.thumbnail_exact(num_cols, num_rows * 2);
         if !self.options.is_color {
             img = img.grayscale();
         }
-        let (num_rows, num_cols) = (img.height() / 2, img.width());
 
         for y in 0..num_rows {
             let upper_y = y * 2;
@@ -51,15 +61,31 @@ impl<'a> UnicodeArt for BlockUnicodeArt<'a> {
             for x in 0..num_cols {
                 let upper_pixel = img.get_pixel(x, upper_y);
                 let lower_pixel = img.get_pixel(x, lower_y);
-                // 24 bits color
-                write!(
-                    writer,
-                    "{}{}\u{2580}", // ▀
-                    upper_pixel.foreground(),
-                    lower_pixel.background(),
-                )?;
+                // transparent halves fall back to the terminal background
+                match (upper_pixel.is_transparent(), lower_pixel.is_transparent()) {
+                    (true, true) => write!(writer, "{} ", ANSI_DEFAULT_BACKGROUND)?,
+                    (false, true) => write!(
+                        writer,
+                        "{}{}\u{2580}", // ▀
+                        upper_pixel.foreground(),
+                        ANSI_DEFAULT_BACKGROUND,
+                    )?,
+                    (true, false) => write!(
+                        writer,
+                        "{}{}\u{2584}", // ▄
+                        lower_pixel.foreground(),
+                        ANSI_DEFAULT_BACKGROUND,
+                    )?,
+                    // 24 bits color
+                    (false, false) => write!(
+                        writer,
+                        "{}{}\u{2580}", // ▀
+                        upper_pixel.foreground(),
+                        lower_pixel.background(),
+                    )?,
+                }
             }
-            writeln!(writer, "{}", ANSI_BG_COLOUR_ESCAPES[0])?;
+            writeln!(writer, "{}", ANSI_DEFAULT_BACKGROUND)?;
         }
         write!(writer, "{}", ANSI_RESET_ATTRIBUTES)?;
         Ok(())
@@ -104,4 +130,24 @@ mod tests {
         // );
         Ok(())
     }
+
+    #[test]
+    fn test_generate_transparent() -> Result<(), UnicodeArtError> {
+        let mut img = image::RgbaImage::new(2, 2);
+        img.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+        img.put_pixel(1, 1, image::Rgba([0, 0, 255, 255]));
+        let image = DynamicImage::ImageRgba8(img);
+        let opt = BlockUnicodeArtOption::new(2, true);
+        let art = opt.new_unicode_art(&image)?;
+        let mut buf = BufWriter::new(Vec::new());
+        art.write_all(&mut buf)?;
+        let bytes = buf.into_inner().unwrap();
+        let actual = String::from_utf8(bytes).unwrap();
+
+        assert_eq!(
+            "\x1B[38;2;255;0;0m\x1B[49m\u{2580}\x1B[38;2;0;0;255m\x1B[49m\u{2584}\x1B[49m\n\x1B[0m",
+            actual
+        );
+        Ok(())
+    }
 }
@@ -1,6 +1,8 @@
 use std::io::Write;
 
-use super::color::{ANSI_BG_COLOUR_ESCAPES, ANSI_RESET_ATTRIBUTES};
+use super::alpha::Transparent;
+use super::aspect_ratio::{AspectRatio, SimpleAspectRatio, TermFit};
+use super::color::{ANSI_DEFAULT_BACKGROUND, ANSI_DEFAULT_COLORS, ANSI_RESET_ATTRIBUTES};
 use super::UnicodeArtOption;
 use super::{color::AnsiColor, error::UnicodeArtError, UnicodeArt};
 use clap::lazy_static::lazy_static;
@@ -12,10 +14,23 @@ const X_DOTS: u8 = 2;
 const Y_DOTS: u8 = 4;
 
 pub const DEFAULT_THRESHOLD: u8 = 127;
+/// The braille pattern without raised dots, every dot adds one bit to it
+pub(super) const BRAILLE_BLANK: u32 = 0x2800;
+/// Dot number to (column, row), dots 7 and 8 were added below the others
+pub(super) const BRAILLE_DOTS: [(u32, u32); 8] = [
+    (0, 0),
+    (0, 1),
+    (0, 2),
+    (1, 0),
+    (1, 1),
+    (1, 2),
+    (0, 3),
+    (1, 3),
+];
 
 pub struct BrailleAsciiArtOption {
     threshold: u8, // range 0 - 255
-    num_cols: u32,
+    aspect_ratio: SimpleAspectRatio,
     is_color: bool,
     is_invert: bool,
 }
@@ -30,6 +45,7 @@ trait BrailleDot {
 }
 
 lazy_static! {
+    // fully transparent, so missing dots are never raised
     static ref PADDING: image::Rgba<u8> = image::Rgba([0u8; 4]);
 }
 
@@ -53,11 +69,16 @@ impl BrailleAsciiArtOption {
     pub fn new(num_cols: u32, threshold: u8, is_color: bool, is_invert: bool) -> Self {
         Self {
             threshold,
-            num_cols,
+            aspect_ratio: SimpleAspectRatio::new_auto_height(num_cols, TermFit::None, false),
             is_color,
             is_invert,
         }
     }
+
+    pub fn with_aspect_ratio(mut self, aspect_ratio: SimpleAspectRatio) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
 }
 
 impl UnicodeArtOption for BrailleAsciiArtOption {
@@ -73,6 +94,19 @@ impl UnicodeArtOption for BrailleAsciiArtOption {
 }
 
 impl<'a> BrailleAsciiArt<'a> {
+    /// Whether a dot is raised; transparent dots are never raised.
+    #[inline]
+    fn to_bit(&self, dot: &Rgba<u8>) -> u8 {
+        if dot.is_transparent() {
+            return 0;
+        }
+        let grey = ((dot[0] as u32 + dot[1] as u32 + dot[2] as u32) / 3) as u8;
+        (match grey < self.options.threshold {
+            true => !self.options.is_invert,
+            false => self.options.is_invert,
+        }) as u8
+    }
+
     pub fn generate_without_color(
         &self,
         img: &DynamicImage,
@@ -90,14 +124,7 @@ impl<'a> BrailleAsciiArt<'a> {
                 );
                 let sub_image = sub_image.to_image();
                 let dots = sub_image.to_dots();
-                let bits = dots
-                    .map(|dot| ((dot[0] as u32 + dot[1] as u32 + dot[2] as u32) / 3) as u8)
-                    .map(|grey| {
-                        (match grey < self.options.threshold {
-                            true => !self.options.is_invert,
-                            false => self.options.is_invert,
-                        }) as u8
-                    });
+                let bits = dots.map(|dot| self.to_bit(dot));
                 let dec = bits.iter().rev().fold(0, |acc, &b| acc * 2 + b as u32);
                 // Braille Unicode range starts at U2800 (= 10240 decimal)
                 let char = char::from_u32(dec + 10240).unwrap();
@@ -132,27 +159,25 @@ impl<'a> BrailleAsciiArt<'a> {
                 // let pixel = sub_image.to_image().resize_exact(1, 1, FilterType::Triangle);
                 let sub_image = sub_image.to_image();
                 let dots = sub_image.to_dots();
-                let bits = dots
-                    // .map(|dot| (dot[0] < self.threshold) as u8);
-                    .map(|dot| ((dot[0] as u32 + dot[1] as u32 + dot[2] as u32) / 3) as u8)
-                    .map(|grey| {
-                        (match grey < self.options.threshold {
-                            true => !self.options.is_invert,
-                            false => self.options.is_invert,
-                        }) as u8
-                    });
+                let bits = dots.map(|dot| self.to_bit(dot));
                 let dec = bits.iter().rev().fold(0, |acc, &b| acc * 2 + b as u32);
                 let char = char::from_u32(dec + 10240).unwrap();
 
-                write!(
-                    writer,
-                    "{}{}{}",
-                    pixel.get_pixel(0, 0).foreground(),
-                    char,
-                    background.background()
-                )?;
+                let pixel = pixel.get_pixel(0, 0);
+                // the previous cell's foreground must not tint the blank
+                if pixel.is_transparent() {
+                    write!(writer, "{}{}", ANSI_DEFAULT_COLORS, char)?;
+                } else {
+                    write!(
+                        writer,
+                        "{}{}{}",
+                        pixel.foreground(),
+                        background.background(),
+                        char
+                    )?;
+                }
             }
-            writeln!(writer, "{}", ANSI_BG_COLOUR_ESCAPES[0])?;
+            writeln!(writer, "{}", ANSI_DEFAULT_BACKGROUND)?;
         }
         write!(writer, "{}", ANSI_RESET_ATTRIBUTES)?;
         Ok(())
@@ -161,9 +186,13 @@ impl<'a> BrailleAsciiArt<'a> {
 
 impl<'a> UnicodeArt for BrailleAsciiArt<'a> {
     fn write_all(&self, writer: &mut dyn Write) -> Result<(), UnicodeArtError> {
+        let (num_cols, num_rows) = self
+            .options
+            .aspect_ratio
+            .calculate(self.image.width(), self.image.height());
         let img = self
             .image
-            .thumbnail(self.options.num_cols * X_DOTS as u32, ::std::u32::MAX);
+            .thumbnail_exact(num_cols * X_DOTS as u32, num_rows * Y_DOTS as u32);
         match self.options.is_color {
             true => self.generate_with_color(&img, writer),
             false => self.generate_without_color(&img, writer),
@@ -178,6 +207,25 @@ mod tests {
     use super::*;
     use std::io::BufWriter;
 
+    #[test]
+    fn test_transparent_color() -> Result<(), UnicodeArtError> {
+        let image = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(4, 4, |x, _| match x < 2 {
+            true => image::Rgba([255, 0, 0, 255]),
+            false => image::Rgba([0, 0, 0, 0]),
+        }));
+        let opt = BrailleAsciiArtOption::new(2, 128, true, false).with_aspect_ratio(
+            SimpleAspectRatio::new(Some(2), Some(1), TermFit::None, false),
+        );
+        let mut buf = Vec::new();
+        opt.new_unicode_art(&image)?.write_all(&mut buf)?;
+        let actual = String::from_utf8(buf).unwrap();
+        assert_eq!(
+            "\x1B[38;2;255;0;0m\x1B[48;2;255;255;255m⣿\x1B[39;49m⠀\x1B[49m\n\x1B[0m",
+            actual
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_generate_braille() -> Result<(), UnicodeArtError> {
         let image_path = "tests/support/test_gundam.png";
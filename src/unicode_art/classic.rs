@@ -1,31 +1,26 @@
+use super::alpha::ALPHA_THRESHOLD;
 use super::aspect_ratio::{AspectRatio, SimpleAspectRatio, TermFit};
-use super::color::{AnsiColor, ANSI_RESET_ATTRIBUTES};
+use super::color::{AnsiColor, ANSI_DEFAULT_BACKGROUND, ANSI_RESET_ATTRIBUTES};
 use super::error::UnicodeArtError;
 use super::mean::Mean;
 use super::{UnicodeArt, UnicodeArtOption};
 use image::{DynamicImage, GenericImageView};
 use std::io::Write;
 
-pub const CHAR_LIST_STANDARD: &'static str =
+pub const CHAR_LIST_STANDARD: &str =
     "$@B%8&WM#*oahkbdpqwmZO0QLCJUYXzcvunxrjft/|()1{}[]?-_+~<>i!lI;:,\"^`'. ";
-pub const CHAR_LIST_LEVELS_10: &'static str = "@%#*+=-:. ";
-pub const CHAR_LIST_LEVELS_19: &'static str = "BBQROHETI)7ri=+;:,.";
-pub const CHAR_LIST_LEVELS_16: &'static str = "#8XOHLTI)i=+;:,.";
-pub const CHAR_LIST_LEVELS_23: &'static str = "MWNXK0Okxdolc:;,'...   ";
-pub const CHAR_LIST_LEVELS_4: &'static str = "3210";
-
-/// ANSI background colour escapes.
-const ANSI_BG_COLOUR_ESCAPES: [&str; 8] = [
-    "\x1B[40m", "\x1B[41m", "\x1B[42m", "\x1B[43m", "\x1B[44m", "\x1B[45m", "\x1B[46m", "\x1B[47m",
-];
+pub const CHAR_LIST_LEVELS_10: &str = "@%#*+=-:. ";
+pub const CHAR_LIST_LEVELS_19: &str = "BBQROHETI)7ri=+;:,.";
+pub const CHAR_LIST_LEVELS_16: &str = "#8XOHLTI)i=+;:,.";
+pub const CHAR_LIST_LEVELS_23: &str = "MWNXK0Okxdolc:;,'...   ";
+pub const CHAR_LIST_LEVELS_4: &str = "3210";
 
 #[derive(Default, Clone)]
 pub struct ClassicAsciiArtOption<'a> {
     pub(crate) is_color: bool,
     pub(crate) is_invert: bool,
     pub(crate) char_list: &'a str,
-    pub(crate) num_cols: Option<u32>,
-    pub(crate) num_rows: Option<u32>,
+    pub(crate) aspect_ratio: SimpleAspectRatio,
 }
 
 pub struct ClassicAsciiArt<'a> {
@@ -36,14 +31,10 @@ pub struct ClassicAsciiArt<'a> {
 impl<'a> ClassicAsciiArt<'a> {
     fn generate_with_color(&self, writer: &mut dyn Write) -> Result<(), UnicodeArtError> {
         let num_chars = self.options.char_list.len();
-        let (num_cols, num_rows) = match (self.options.num_cols, self.options.num_rows) {
-            (Some(cols), Some(rows)) => (cols, rows),
-            (Some(cols), None) => SimpleAspectRatio::new_auto_height(cols, TermFit::Auto, false)
-                .calculate(self.image.width(), self.image.height()),
-            (None, Some(rows)) => SimpleAspectRatio::new_auto_width(rows, TermFit::Auto, false)
-                .calculate(self.image.width(), self.image.height()),
-            _ => (self.image.width(), self.image.height()),
-        };
+        let (num_cols, num_rows) = self
+            .options
+            .aspect_ratio
+            .calculate(self.image.width(), self.image.height());
         let background = &image::Rgba([0u8; 4]);
 
         let x_ratio = (self.image.width() - 1) as f64 / num_cols as f64;
@@ -55,6 +46,10 @@ impl<'a> ClassicAsciiArt<'a> {
                 let ey = (((i + 1) as f64) * y_ratio).round() as u32;
                 let sx = (j as f64 * x_ratio).round() as u32;
                 let ex = (((j + 1) as f64) * x_ratio).round() as u32;
+                if self.image.mean_alpha(sx, ex, sy, ey) < ALPHA_THRESHOLD {
+                    write!(writer, "{} ", ANSI_DEFAULT_BACKGROUND)?;
+                    continue;
+                }
                 let mean = self.image.mean(sx, ex, sy, ey);
                 let upper_pixel = self.image.get_pixel(sx, sy);
                 let char_idx = (num_chars - 1).min(mean as usize * num_chars / 255);
@@ -67,7 +62,7 @@ impl<'a> ClassicAsciiArt<'a> {
                     char
                 )?;
             }
-            writeln!(writer, "{}", ANSI_BG_COLOUR_ESCAPES[0])?;
+            writeln!(writer, "{}", ANSI_DEFAULT_BACKGROUND)?;
         }
         write!(writer, "{}", ANSI_RESET_ATTRIBUTES)?;
 
@@ -76,14 +71,10 @@ impl<'a> ClassicAsciiArt<'a> {
 
     fn generate_with_grayscale(&self, writer: &mut dyn Write) -> Result<(), UnicodeArtError> {
         let num_chars = self.options.char_list.len();
-        let (num_cols, num_rows) = match (self.options.num_cols, self.options.num_rows) {
-            (Some(cols), Some(rows)) => (cols, rows),
-            (Some(cols), None) => SimpleAspectRatio::new_auto_height(cols, TermFit::Auto, false)
-                .calculate(self.image.width(), self.image.height()),
-            (None, Some(rows)) => SimpleAspectRatio::new_auto_width(rows, TermFit::Auto, false)
-                .calculate(self.image.width(), self.image.height()),
-            _ => (self.image.width(), self.image.height()),
-        };
+        let (num_cols, num_rows) = self
+            .options
+            .aspect_ratio
+            .calculate(self.image.width(), self.image.height());
 
         let x_ratio = (self.image.width() - 1) as f64 / num_cols as f64;
         let y_ratio = (self.image.height() - 1) as f64 / num_rows as f64;
@@ -95,6 +86,10 @@ impl<'a> ClassicAsciiArt<'a> {
                 let sx = (j as f64 * x_ratio).round() as u32;
                 let ex = (((j + 1) as f64) * x_ratio).round() as u32;
                 // println!("sx = {}, sy = {}, ex = {}, ey = {}", sx, sy, ex, ey);
+                if self.image.mean_alpha(sx, ex, sy, ey) < ALPHA_THRESHOLD {
+                    write!(writer, " ")?;
+                    continue;
+                }
                 let mean = match self.options.is_invert {
                     true => 255 - self.image.mean(sx, ex, sy, ey),
                     false => self.image.mean(sx, ex, sy, ey),
@@ -113,7 +108,7 @@ impl UnicodeArtOption for ClassicAsciiArtOption<'static> {
     fn new_unicode_art<'a>(
         &'a self,
         image: &'a DynamicImage,
-    ) -> Result<Box<(dyn UnicodeArt + 'a)>, UnicodeArtError> {
+    ) -> Result<Box<dyn UnicodeArt + 'a>, UnicodeArtError> {
         Ok(Box::new(ClassicAsciiArt {
             options: self,
             image,
@@ -122,11 +117,15 @@ impl UnicodeArtOption for ClassicAsciiArtOption<'static> {
 }
 
 impl<'a> ClassicAsciiArtOption<'a> {
+    pub fn with_aspect_ratio(mut self, aspect_ratio: SimpleAspectRatio) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
     pub fn new_standard(num_cols: u32, is_color: bool, is_invert: bool) -> Self {
         Self {
             char_list: CHAR_LIST_STANDARD,
-            num_cols: Some(num_cols),
-            num_rows: None,
+            aspect_ratio: SimpleAspectRatio::new_auto_height(num_cols, TermFit::None, false),
             is_color,
             is_invert,
         }
@@ -135,8 +134,7 @@ impl<'a> ClassicAsciiArtOption<'a> {
     pub fn new_level_4(num_cols: u32, is_color: bool, is_invert: bool) -> Self {
         Self {
             char_list: CHAR_LIST_LEVELS_4,
-            num_cols: Some(num_cols),
-            num_rows: None,
+            aspect_ratio: SimpleAspectRatio::new_auto_height(num_cols, TermFit::None, false),
             is_color,
             is_invert,
         }
@@ -145,8 +143,7 @@ impl<'a> ClassicAsciiArtOption<'a> {
     pub fn new_level_10(num_cols: u32, is_color: bool, is_invert: bool) -> Self {
         Self {
             char_list: CHAR_LIST_LEVELS_10,
-            num_cols: Some(num_cols),
-            num_rows: None,
+            aspect_ratio: SimpleAspectRatio::new_auto_height(num_cols, TermFit::None, false),
             is_color,
             is_invert,
         }
@@ -155,8 +152,7 @@ impl<'a> ClassicAsciiArtOption<'a> {
     pub fn new_level_19(num_cols: u32, is_color: bool, is_invert: bool) -> Self {
         Self {
             char_list: CHAR_LIST_LEVELS_19,
-            num_cols: Some(num_cols),
-            num_rows: None,
+            aspect_ratio: SimpleAspectRatio::new_auto_height(num_cols, TermFit::None, false),
             is_color,
             is_invert,
         }
@@ -165,8 +161,7 @@ impl<'a> ClassicAsciiArtOption<'a> {
     pub fn new_level_16(num_cols: u32, is_color: bool, is_invert: bool) -> Self {
         Self {
             char_list: CHAR_LIST_LEVELS_16,
-            num_cols: Some(num_cols),
-            num_rows: None,
+            aspect_ratio: SimpleAspectRatio::new_auto_height(num_cols, TermFit::None, false),
             is_color,
             is_invert,
         }
@@ -175,8 +170,7 @@ impl<'a> ClassicAsciiArtOption<'a> {
     pub fn new_level_23(num_cols: u32, is_color: bool, is_invert: bool) -> Self {
         Self {
             char_list: CHAR_LIST_LEVELS_23,
-            num_cols: Some(num_cols),
-            num_rows: None,
+            aspect_ratio: SimpleAspectRatio::new_auto_height(num_cols, TermFit::None, false),
             is_color,
             is_invert,
         }
@@ -272,7 +266,7 @@ $$$$$@@$$$$$$$$$$$$$
         let opt = ClassicAsciiArtOption::new_level_10(20, false, false);
         let art = opt.new_unicode_art(&image)?;
         let mut buf = BufWriter::new(Vec::new());
-        let _ = art.write_all(&mut buf)?;
+        art.write_all(&mut buf)?;
         let bytes = buf.into_inner().unwrap();
         let actual = String::from_utf8(bytes).unwrap();
 
@@ -1,11 +1,11 @@
-use image::Rgba;
+use image::{Rgb, Rgba};
 
-/// ANSI background colour escapes.
-pub const ANSI_BG_COLOUR_ESCAPES: [&str; 8] = [
-    "\x1B[40m", "\x1B[41m", "\x1B[42m", "\x1B[43m", "\x1B[44m", "\x1B[45m", "\x1B[46m", "\x1B[47m",
-];
 /// Reset ANSI attributes
-pub const ANSI_RESET_ATTRIBUTES: &'static str = "\x1B[0m";
+pub const ANSI_RESET_ATTRIBUTES: &str = "\x1B[0m";
+/// Restore the terminal's default background colour
+pub const ANSI_DEFAULT_BACKGROUND: &str = "\x1B[49m";
+/// Restore the terminal's default foreground and background colours
+pub const ANSI_DEFAULT_COLORS: &str = "\x1B[39;49m";
 
 pub trait AnsiColor {
     fn foreground(&self) -> String;
@@ -23,3 +23,140 @@ impl AnsiColor for Rgba<u8> {
         format!("\x1B[48;2;{};{};{}m", self[0], self[1], self[2])
     }
 }
+
+/// Parse a colour written as `#rgb`, `#rrggbb` (the `#` is optional) or one of
+/// a few common names.
+pub fn parse_color(value: &str) -> Option<Rgba<u8>> {
+    match value.to_ascii_lowercase().as_str() {
+        "black" => return Some(Rgba([0, 0, 0, 255])),
+        "white" => return Some(Rgba([255, 255, 255, 255])),
+        "red" => return Some(Rgba([255, 0, 0, 255])),
+        "green" => return Some(Rgba([0, 255, 0, 255])),
+        "blue" => return Some(Rgba([0, 0, 255, 255])),
+        _ => {}
+    }
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+    match hex.len() {
+        3 => {
+            let mut rgb = [0u8; 3];
+            for (i, c) in hex.chars().enumerate() {
+                rgb[i] = channel(&c.to_string())? * 17;
+            }
+            Some(Rgba([rgb[0], rgb[1], rgb[2], 255]))
+        }
+        6 => Some(Rgba([
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+            255,
+        ])),
+        _ => None,
+    }
+}
+
+/// The 16 VGA colours in ANSI order: black, red, green, brown, blue,
+/// magenta, cyan, grey, followed by the bright variants.
+pub const VGA_PALETTE: [[u8; 3]; 16] = [
+    [0x00, 0x00, 0x00],
+    [0xAA, 0x00, 0x00],
+    [0x00, 0xAA, 0x00],
+    [0xAA, 0x55, 0x00],
+    [0x00, 0x00, 0xAA],
+    [0xAA, 0x00, 0xAA],
+    [0x00, 0xAA, 0xAA],
+    [0xAA, 0xAA, 0xAA],
+    [0x55, 0x55, 0x55],
+    [0xFF, 0x55, 0x55],
+    [0x55, 0xFF, 0x55],
+    [0xFF, 0xFF, 0x55],
+    [0x55, 0x55, 0xFF],
+    [0xFF, 0x55, 0xFF],
+    [0x55, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0xFF],
+];
+
+/// A colour of the 256 colour palette: the 16 basic colours, a 6x6x6 cube
+/// and a grey ramp.
+pub fn ansi_256(index: u8) -> Rgb<u8> {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    match index {
+        0..=15 => Rgb(VGA_PALETTE[index as usize]),
+        16..=231 => {
+            let i = index - 16;
+            Rgb([
+                LEVELS[(i / 36) as usize],
+                LEVELS[(i / 6 % 6) as usize],
+                LEVELS[(i % 6) as usize],
+            ])
+        }
+        _ => {
+            let grey = 8 + (index - 232) * 10;
+            Rgb([grey, grey, grey])
+        }
+    }
+}
+
+/// CIELAB coordinates of an sRGB colour under the D65 white point, where
+/// distances roughly match how different colours look.
+pub fn to_lab(color: Rgb<u8>) -> [f64; 3] {
+    // D65 reference white
+    const WHITE: [f64; 3] = [0.95047, 1.0, 1.08883];
+    let linear = |c: u8| {
+        let c = c as f64 / 255.0;
+        match c <= 0.04045 {
+            true => c / 12.92,
+            false => ((c + 0.055) / 1.055).powf(2.4),
+        }
+    };
+    let [r, g, b] = [linear(color[0]), linear(color[1]), linear(color[2])];
+    let xyz = [
+        0.4124 * r + 0.3576 * g + 0.1805 * b,
+        0.2126 * r + 0.7152 * g + 0.0722 * b,
+        0.0193 * r + 0.1192 * g + 0.9505 * b,
+    ];
+    let f = |t: f64| match t > 216.0 / 24389.0 {
+        true => t.cbrt(),
+        false => (24389.0 / 27.0 * t + 16.0) / 116.0,
+    };
+    let [fx, fy, fz] = [
+        f(xyz[0] / WHITE[0]),
+        f(xyz[1] / WHITE[1]),
+        f(xyz[2] / WHITE[2]),
+    ];
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#ff8000"), Some(Rgba([255, 128, 0, 255])));
+        assert_eq!(parse_color("0a0B0c"), Some(Rgba([10, 11, 12, 255])));
+        assert_eq!(parse_color("#fff"), Some(Rgba([255, 255, 255, 255])));
+        assert_eq!(parse_color("Black"), Some(Rgba([0, 0, 0, 255])));
+        assert_eq!(parse_color("#12345"), None);
+        assert_eq!(parse_color("none"), None);
+    }
+
+    #[test]
+    fn test_ansi_256() {
+        assert_eq!(Rgb([0xAA, 0, 0]), ansi_256(1));
+        assert_eq!(Rgb([255, 135, 0]), ansi_256(208));
+        assert_eq!(Rgb([238, 238, 238]), ansi_256(255));
+    }
+
+    #[test]
+    fn test_to_lab() {
+        let round = |color| to_lab(color).map(|v| v.round());
+        assert_eq!([0.0, 0.0, 0.0], round(Rgb([0, 0, 0])));
+        assert_eq!([100.0, 0.0, 0.0], round(Rgb([255, 255, 255])));
+        assert_eq!([53.0, 80.0, 67.0], round(Rgb([255, 0, 0])));
+        assert_eq!([32.0, 79.0, -108.0], round(Rgb([0, 0, 255])));
+    }
+}
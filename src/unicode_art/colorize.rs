@@ -0,0 +1,253 @@
+use std::f64::consts::PI;
+use std::io::Write;
+use std::str::FromStr;
+
+use image::{DynamicImage, Rgb, Rgba};
+
+use super::aspect_ratio::DEFAULT_CELL_ASPECT;
+use super::braille::BRAILLE_BLANK;
+use super::color::parse_color;
+use super::error::UnicodeArtError;
+use super::gradient::Gradient;
+use super::grid::CellGrid;
+use super::{UnicodeArt, UnicodeArtOption};
+
+/// Radians the rainbow turns from one cell to the next, like lolcat
+const RAINBOW_FREQUENCY: f64 = 0.1;
+/// Columns a row counts as, so that diagonal and radial tints look even
+const ROW_WEIGHT: f64 = 1.0 / DEFAULT_CELL_ASPECT;
+
+/// Where the tint starts and which way it runs over the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// From the left to the right
+    Horizontal,
+    /// From the top to the bottom
+    Vertical,
+    /// From the top left to the bottom right
+    Diagonal,
+    /// From the center outwards
+    Radial,
+}
+
+impl FromStr for Direction {
+    type Err = UnicodeArtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "horizontal" => Ok(Direction::Horizontal),
+            "vertical" => Ok(Direction::Vertical),
+            "diagonal" => Ok(Direction::Diagonal),
+            "radial" => Ok(Direction::Radial),
+            _ => Err(UnicodeArtError::InvalidArgumentError(format!(
+                "invalid direction {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl Direction {
+    /// Distance of a cell from the start, and the largest distance in a
+    /// `width` × `height` grid, both in columns.
+    fn distance(&self, row: usize, col: usize, width: usize, height: usize) -> (f64, f64) {
+        let (x, y) = (col as f64, row as f64 * ROW_WEIGHT);
+        let last_x = width.saturating_sub(1) as f64;
+        let last_y = height.saturating_sub(1) as f64 * ROW_WEIGHT;
+        match self {
+            Direction::Horizontal => (x, last_x),
+            Direction::Vertical => (y, last_y),
+            Direction::Diagonal => (x + y, last_x + last_y),
+            Direction::Radial => {
+                let (center_x, center_y) = (last_x / 2.0, last_y / 2.0);
+                ((x - center_x).hypot(y - center_y), center_x.hypot(center_y))
+            }
+        }
+    }
+}
+
+/// Colors a colorizer paints with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tint {
+    /// Stretched over the whole grid
+    Gradient(Gradient),
+    /// Repeats every few dozen cells, whatever the size of the grid
+    Rainbow,
+    /// Equal bands of fixed colors
+    Palette(Vec<Rgb<u8>>),
+}
+
+impl FromStr for Tint {
+    type Err = UnicodeArtError;
+
+    /// `rainbow`, `palette:` and a list of colors, or a gradient.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "rainbow" {
+            return Ok(Tint::Rainbow);
+        }
+        let colors = match s.strip_prefix("palette:") {
+            Some(colors) => colors,
+            None => return Ok(Tint::Gradient(s.parse()?)),
+        };
+        colors
+            .split(',')
+            .map(|color| parse_color(color.trim()).map(|Rgba([r, g, b, _])| Rgb([r, g, b])))
+            .collect::<Option<Vec<_>>>()
+            .map(Tint::Palette)
+            .ok_or_else(|| UnicodeArtError::InvalidArgumentError(format!("invalid palette {}", s)))
+    }
+}
+
+/// The rainbow `phase` radians in, three sine waves a third apart.
+fn rainbow(phase: f64) -> Rgb<u8> {
+    let channel = |offset: f64| ((phase + offset).sin() * 127.0 + 128.0).round() as u8;
+    Rgb([
+        channel(0.0),
+        channel(2.0 * PI / 3.0),
+        channel(4.0 * PI / 3.0),
+    ])
+}
+
+/// Tints rendered art after the fact, whatever colors the source had.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Colorizer {
+    tint: Tint,
+    direction: Direction,
+}
+
+impl Colorizer {
+    pub fn new(tint: Tint, direction: Direction) -> Self {
+        Self { tint, direction }
+    }
+
+    fn color_at(&self, row: usize, col: usize, width: usize, height: usize) -> Rgb<u8> {
+        let (distance, last) = self.direction.distance(row, col, width, height);
+        let t = match last > 0.0 {
+            true => distance / last,
+            false => 0.0,
+        };
+        match &self.tint {
+            Tint::Gradient(gradient) => {
+                let Rgba([r, g, b, _]) = gradient.at(t);
+                Rgb([r, g, b])
+            }
+            Tint::Rainbow => rainbow(distance * RAINBOW_FREQUENCY),
+            Tint::Palette(colors) => {
+                let band = (t * colors.len() as f64) as usize;
+                colors[band.min(colors.len() - 1)]
+            }
+        }
+    }
+
+    /// Paint the foreground of every visible character, backgrounds are
+    /// kept so half blocks still show their lower half.
+    pub fn colorize(&self, text: &str) -> String {
+        let mut grid = CellGrid::parse(text);
+        let (width, height) = (grid.width(), grid.height());
+        for row in 0..height {
+            for col in 0..width {
+                let mut cell = grid.get(row, col);
+                if cell.ch == ' ' || cell.ch as u32 == BRAILLE_BLANK {
+                    continue;
+                }
+                cell.fg = Some(self.color_at(row, col, width, height));
+                grid.set(row, col, cell);
+            }
+        }
+        grid.to_ansi()
+    }
+}
+
+/// An image renderer whose art is tinted by a colorizer.
+pub struct ColorizedOption<'a> {
+    option: &'a dyn UnicodeArtOption,
+    colorizer: Colorizer,
+}
+
+impl<'a> ColorizedOption<'a> {
+    pub fn new(option: &'a dyn UnicodeArtOption, colorizer: Colorizer) -> Self {
+        Self { option, colorizer }
+    }
+}
+
+impl<'a> UnicodeArtOption for ColorizedOption<'a> {
+    fn new_unicode_art<'b>(
+        &'b self,
+        image: &'b DynamicImage,
+    ) -> Result<Box<dyn UnicodeArt + 'b>, UnicodeArtError> {
+        Ok(Box::new(ColorizedArt {
+            art: self.option.new_unicode_art(image)?,
+            colorizer: &self.colorizer,
+        }))
+    }
+}
+
+pub struct ColorizedArt<'a> {
+    art: Box<dyn UnicodeArt + 'a>,
+    colorizer: &'a Colorizer,
+}
+
+impl<'a> UnicodeArt for ColorizedArt<'a> {
+    fn write_all(&self, writer: &mut dyn Write) -> Result<(), UnicodeArtError> {
+        let mut buf = Vec::new();
+        self.art.write_all(&mut buf)?;
+        let text = String::from_utf8_lossy(&buf);
+        Ok(writer.write_all(self.colorizer.colorize(&text).as_bytes())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unicode_art::braille::BrailleAsciiArtOption;
+
+    #[test]
+    fn test_colorize() -> Result<(), UnicodeArtError> {
+        let colorizer = Colorizer::new("palette:#f00,#00f".parse()?, Direction::Horizontal);
+        assert_eq!(
+            "\x1B[38;2;255;0;0ma\x1B[0m \x1B[38;2;0;0;255mb\x1B[0m\n\
+             \x1B[38;2;255;0;0m\u{2580}\x1B[38;2;0;0;255;48;2;1;2;3mc\x1B[0m\n",
+            colorizer.colorize("a b\n\u{2580}\x1B[48;2;1;2;3mc\x1B[0m\n")
+        );
+
+        let colorizer = Colorizer::new("#000,#fff".parse()?, Direction::Vertical);
+        assert_eq!(
+            "\x1B[38;2;0;0;0mx\x1B[0m\n\x1B[38;2;255;255;255my\x1B[0m\n",
+            colorizer.colorize("x\ny")
+        );
+        for invalid in ["palette:", "palette:#f00,nope", "nope"] {
+            assert!(invalid.parse::<Tint>().is_err(), "{}", invalid);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_directions() {
+        let distance = |direction: Direction, row, col| direction.distance(row, col, 5, 3);
+        assert_eq!((2.0, 4.0), distance(Direction::Horizontal, 1, 2));
+        assert_eq!((2.0, 4.0), distance(Direction::Vertical, 1, 2));
+        assert_eq!((4.0, 8.0), distance(Direction::Diagonal, 1, 2));
+        assert_eq!((0.0, 8f64.sqrt()), distance(Direction::Radial, 1, 2));
+        assert_eq!(Rgb([128, 238, 18]), rainbow(0.0));
+    }
+
+    #[test]
+    fn test_colorized_option() -> Result<(), UnicodeArtError> {
+        let image = DynamicImage::new_rgb8(8, 8);
+        let braille = BrailleAsciiArtOption::new(2, 128, false, false);
+        let colorizer = Colorizer::new(Tint::Rainbow, Direction::Horizontal);
+        let option = ColorizedOption::new(&braille, colorizer);
+        let mut buf = Vec::new();
+        option.new_unicode_art(&image)?.write_all(&mut buf)?;
+        let actual = String::from_utf8(buf).unwrap();
+        assert_eq!(
+            "\x1B[38;2;128;238;18m\u{28FF}\x1B[38;2;141;231;12m\u{28FF}\x1B[0m\n",
+            actual
+                .lines()
+                .next()
+                .map(|line| format!("{}\n", line))
+                .unwrap()
+        );
+        Ok(())
+    }
+}
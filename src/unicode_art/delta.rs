@@ -0,0 +1,170 @@
+use std::fmt::Write;
+
+use super::animation::ANSI_CLEAR_SCREEN;
+use super::grid::{CellGrid, Style};
+
+/// Redraw everything once more than this share of the cells changed, cursor
+/// moves would cost more than they save.
+pub const DEFAULT_REDRAW_THRESHOLD: f64 = 0.5;
+
+/// Turns successive frames into the minimal output that updates the screen
+/// from the previous frame: cursor moves plus the cells that changed.
+pub struct DeltaEncoder {
+    previous: Option<CellGrid>,
+    redraw_threshold: f64,
+}
+
+impl Default for DeltaEncoder {
+    fn default() -> Self {
+        Self::new(DEFAULT_REDRAW_THRESHOLD)
+    }
+}
+
+impl DeltaEncoder {
+    pub fn new(redraw_threshold: f64) -> Self {
+        Self {
+            previous: None,
+            redraw_threshold,
+        }
+    }
+
+    /// Output bringing the screen from the previous frame to `frame`, which
+    /// is the renderer output of the whole frame.
+    pub fn encode(&mut self, frame: &str) -> String {
+        let grid = CellGrid::parse(frame);
+        let all: Vec<_> = (0..grid.height())
+            .flat_map(|row| (0..grid.width()).map(move |col| (row, col)))
+            .collect();
+        let output = match &self.previous {
+            Some(previous)
+                if previous.width() == grid.width() && previous.height() == grid.height() =>
+            {
+                let changed: Vec<_> = all
+                    .iter()
+                    .copied()
+                    .filter(|&(row, col)| previous.get(row, col) != grid.get(row, col))
+                    .collect();
+                let total = all.len().max(1);
+                match changed.len() as f64 / total as f64 > self.redraw_threshold {
+                    true => Self::update(&grid, &all),
+                    false => Self::update(&grid, &changed),
+                }
+            }
+            // a frame of another size leaves stale cells around
+            _ => ANSI_CLEAR_SCREEN.to_string() + &Self::update(&grid, &all),
+        };
+        self.previous = Some(grid);
+        output
+    }
+
+    fn update(grid: &CellGrid, cells: &[(usize, usize)]) -> String {
+        let mut output = String::new();
+        let mut style = Style::default();
+        let mut cursor = None;
+        for &(row, col) in cells {
+            let cell = grid.get(row, col);
+            // drawn along with the wide character on its left
+            if cell.is_wide_tail() {
+                continue;
+            }
+            if cursor != Some((row, col)) {
+                write!(output, "\x1B[{};{}H", row + 1, col + 1).unwrap();
+            }
+            let target = Style {
+                fg: cell.fg,
+                bg: cell.bg,
+            };
+            output.push_str(&style.transition(&target));
+            output.push(cell.ch);
+            style = target;
+            cursor = match grid.get(row, col + 1).is_wide_tail() {
+                true => Some((row, col + 2)),
+                false => Some((row, col + 1)),
+            };
+        }
+        // leave the terminal with default attributes between frames
+        output.push_str(&style.transition(&Style::default()));
+        // park the cursor below the art, where a full frame would leave it
+        write!(output, "\x1B[{};1H", grid.height() + 1).unwrap();
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unicode_art::block::BlockUnicodeArtOption;
+    use crate::unicode_art::braille::BrailleAsciiArtOption;
+    use crate::unicode_art::emoji::EmojiUnicodeArtOption;
+    use crate::unicode_art::error::UnicodeArtError;
+    use crate::unicode_art::UnicodeArtOption;
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    fn render(option: &dyn UnicodeArtOption, image: &DynamicImage) -> String {
+        let mut buf = Vec::new();
+        option
+            .new_unicode_art(image)
+            .unwrap()
+            .write_all(&mut buf)
+            .unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    // a bright square moving over a gradient
+    fn frames(count: u32) -> Vec<DynamicImage> {
+        (0..count)
+            .map(|i| {
+                DynamicImage::ImageRgba8(RgbaImage::from_fn(32, 32, |x, y| {
+                    match x / 4 == i && y / 4 == i {
+                        true => Rgba([255, 255, 255, 255]),
+                        false => Rgba([(x * 8) as u8, (y * 8) as u8, 128, 255]),
+                    }
+                }))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_replay() -> Result<(), UnicodeArtError> {
+        let options: Vec<Box<dyn UnicodeArtOption>> = vec![
+            Box::new(BlockUnicodeArtOption::new(16, true)),
+            Box::new(BrailleAsciiArtOption::new(16, 128, true, false)),
+            Box::new(BrailleAsciiArtOption::new(16, 128, false, false)),
+            Box::new(EmojiUnicodeArtOption::new(16)),
+        ];
+        for option in options {
+            let mut encoder = DeltaEncoder::default();
+            let mut screen = CellGrid::default();
+            let mut full_size = 0;
+            let mut delta_size = 0;
+            for image in frames(6) {
+                let frame = render(option.as_ref(), &image);
+                let delta = encoder.encode(&frame);
+                screen.apply(&delta);
+                assert!(screen.same_cells(&CellGrid::parse(&frame)));
+                full_size += frame.len();
+                delta_size += delta.len();
+            }
+            assert!(delta_size < full_size);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_redraw_threshold() {
+        let mut encoder = DeltaEncoder::new(0.25);
+        assert_eq!(
+            "\x1B[2J\x1B[1;1Hab\x1B[2;1Hcd\x1B[3;1H",
+            encoder.encode("ab\ncd\n")
+        );
+        // one of four cells changed
+        assert_eq!("\x1B[1;2Hx\x1B[3;1H", encoder.encode("ax\ncd\n"));
+        // two of four
+        assert_eq!(
+            "\x1B[1;1Hyx\x1B[2;1Hcz\x1B[3;1H",
+            encoder.encode("yx\ncz\n")
+        );
+        // a new size clears the screen
+        assert_eq!("\x1B[2J\x1B[1;1Hyx\x1B[2;1H", encoder.encode("yx\n"));
+    }
+}
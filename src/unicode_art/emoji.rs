@@ -0,0 +1,211 @@
+use super::alpha::ALPHA_THRESHOLD;
+use super::aspect_ratio::{AspectRatio, SimpleAspectRatio, TermFit};
+use super::color::to_lab;
+use super::error::UnicodeArtError;
+use super::mean::Mean;
+use super::{UnicodeArt, UnicodeArtOption};
+use image::{DynamicImage, Rgb};
+use std::io::Write;
+
+/// Terminals draw emoji two columns wide
+pub const EMOJI_COLUMNS: u32 = 2;
+
+/// Emoji and the mean color of their opaque pixels, measured on common emoji
+/// fonts. Fonts differ, so the colors are only a close guess for any one
+/// terminal. Every emoji is drawn wide without a variation selector.
+pub const EMOJI_PALETTE: [(char, [u8; 3]); 48] = [
+    ('🟥', [0xF4, 0x43, 0x36]),
+    ('🟧', [0xFF, 0x98, 0x00]),
+    ('🟨', [0xFD, 0xD8, 0x35]),
+    ('🟩', [0x7C, 0xB3, 0x42]),
+    ('🟦', [0x1E, 0x88, 0xE5]),
+    ('🟪', [0x8E, 0x24, 0xAA]),
+    ('🟫', [0x79, 0x55, 0x48]),
+    ('⬛', [0x21, 0x21, 0x21]),
+    ('⬜', [0xEE, 0xEE, 0xEE]),
+    ('🍎', [0xD3, 0x2F, 0x2F]),
+    ('🍊', [0xF5, 0x8A, 0x1F]),
+    ('🍋', [0xF8, 0xD5, 0x3A]),
+    ('🍏', [0x8B, 0xC3, 0x4A]),
+    ('🍇', [0x7B, 0x3F, 0x8C]),
+    ('🫐', [0x3F, 0x51, 0x8C]),
+    ('🍑', [0xF7, 0xA0, 0x6B]),
+    ('🥑', [0x7C, 0x9A, 0x3D]),
+    ('🍆', [0x5E, 0x35, 0x7A]),
+    ('🌽', [0xE8, 0xC2, 0x3C]),
+    ('🥕', [0xF0, 0x7C, 0x22]),
+    ('🍄', [0xD8, 0x4A, 0x3A]),
+    ('🍫', [0x6D, 0x3B, 0x2A]),
+    ('🍞', [0xD9, 0xA0, 0x5B]),
+    ('🧀', [0xF9, 0xC6, 0x4B]),
+    ('🍚', [0xEC, 0xEC, 0xE6]),
+    ('🦴', [0xE6, 0xDE, 0xCE]),
+    ('☕', [0x8D, 0x6E, 0x63]),
+    ('🧊', [0xB3, 0xE0, 0xF2]),
+    ('🌊', [0x2F, 0x80, 0xD0]),
+    ('💧', [0x4F, 0xA8, 0xE8]),
+    ('🌲', [0x2E, 0x7D, 0x32]),
+    ('🌵', [0x66, 0xA0, 0x3A]),
+    ('🌻', [0xE8, 0xB0, 0x20]),
+    ('🌸', [0xF4, 0xB6, 0xC8]),
+    ('🔥', [0xF5, 0x7C, 0x1F]),
+    ('🌑', [0x42, 0x42, 0x42]),
+    ('🌃', [0x2A, 0x33, 0x5C]),
+    ('🐷', [0xF2, 0xA7, 0xB5]),
+    ('🐸', [0x7A, 0xB5, 0x4A]),
+    ('🐻', [0x8D, 0x5B, 0x3B]),
+    ('🐼', [0x9E, 0x9E, 0x9E]),
+    ('🐘', [0x9A, 0xA7, 0xB0]),
+    ('🦊', [0xE0, 0x7B, 0x2E]),
+    ('🐙', [0xE5, 0x5A, 0x7B]),
+    ('🦩', [0xF2, 0x8C, 0xA8]),
+    ('💜', [0x9C, 0x27, 0xB0]),
+    ('💙', [0x21, 0x96, 0xF3]),
+    ('💚', [0x4C, 0xAF, 0x50]),
+];
+
+pub struct EmojiUnicodeArtOption {
+    aspect_ratio: SimpleAspectRatio,
+    // the palette in CIELAB, computed once for every cell to compare with
+    palette: Vec<(char, [f64; 3])>,
+}
+
+impl EmojiUnicodeArtOption {
+    /// `num_cols` counts terminal columns, the art has half as many emoji.
+    pub fn new(num_cols: u32) -> Self {
+        Self {
+            aspect_ratio: SimpleAspectRatio::new_auto_height(num_cols, TermFit::None, false)
+                .with_cell_columns(EMOJI_COLUMNS),
+            palette: EMOJI_PALETTE
+                .iter()
+                .map(|&(emoji, color)| (emoji, to_lab(Rgb(color))))
+                .collect(),
+        }
+    }
+
+    /// The cells of `aspect_ratio` are made two columns wide.
+    pub fn with_aspect_ratio(mut self, aspect_ratio: SimpleAspectRatio) -> Self {
+        self.aspect_ratio = aspect_ratio.with_cell_columns(EMOJI_COLUMNS);
+        self
+    }
+
+    /// The emoji that looks most like `color`, by CIE76 color difference.
+    fn nearest(&self, color: Rgb<u8>) -> char {
+        let [l, a, b] = to_lab(color);
+        let distance =
+            |lab: &[f64; 3]| (lab[0] - l).powi(2) + (lab[1] - a).powi(2) + (lab[2] - b).powi(2);
+        self.palette
+            .iter()
+            .min_by(|(_, x), (_, y)| distance(x).total_cmp(&distance(y)))
+            .map(|&(emoji, _)| emoji)
+            .unwrap()
+    }
+}
+
+impl UnicodeArtOption for EmojiUnicodeArtOption {
+    fn new_unicode_art<'a>(
+        &'a self,
+        image: &'a DynamicImage,
+    ) -> Result<Box<dyn UnicodeArt + 'a>, UnicodeArtError> {
+        Ok(Box::new(EmojiUnicodeArt {
+            options: self,
+            image,
+        }))
+    }
+}
+
+pub struct EmojiUnicodeArt<'a> {
+    options: &'a EmojiUnicodeArtOption,
+    image: &'a DynamicImage,
+}
+
+impl<'a> UnicodeArt for EmojiUnicodeArt<'a> {
+    fn write_all(&self, writer: &mut dyn Write) -> Result<(), UnicodeArtError> {
+        let (num_cols, num_rows) = self
+            .options
+            .aspect_ratio
+            .calculate(self.image.width(), self.image.height());
+        let x_ratio = self.image.width() as f64 / num_cols as f64;
+        let y_ratio = self.image.height() as f64 / num_rows as f64;
+
+        // cells smaller than a pixel still cover the pixel they start in
+        let span = |i: u32, ratio: f64, size: u32| {
+            let start = ((i as f64 * ratio) as u32).min(size - 1);
+            let end = (((i + 1) as f64 * ratio) as u32).max(start + 1);
+            (start, end)
+        };
+        for i in 0..num_rows {
+            let (sy, ey) = span(i, y_ratio, self.image.height());
+            for j in 0..num_cols {
+                let (sx, ex) = span(j, x_ratio, self.image.width());
+                // an emoji is as wide as two spaces
+                if self.image.mean_alpha(sx, ex, sy, ey) < ALPHA_THRESHOLD {
+                    write!(writer, "  ")?;
+                    continue;
+                }
+                let color = self.image.mean_color(sx, ex, sy, ey);
+                write!(writer, "{}", self.options.nearest(color))?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_nearest() {
+        let option = EmojiUnicodeArtOption::new(10);
+        assert_eq!('🟥', option.nearest(Rgb([255, 0, 0])));
+        assert_eq!('⬛', option.nearest(Rgb([0, 0, 0])));
+        assert_eq!('⬜', option.nearest(Rgb([255, 255, 255])));
+        assert_eq!('🟦', option.nearest(Rgb([0, 120, 230])));
+    }
+
+    #[test]
+    fn test_transparent() -> Result<(), UnicodeArtError> {
+        let mut image = RgbaImage::from_pixel(4, 2, Rgba([0, 0, 0, 0]));
+        for y in 0..2 {
+            image.put_pixel(0, y, Rgba([250, 215, 50, 255]));
+            image.put_pixel(1, y, Rgba([250, 215, 50, 255]));
+        }
+        let image = DynamicImage::ImageRgba8(image);
+        let option = EmojiUnicodeArtOption::new(4).with_aspect_ratio(SimpleAspectRatio::new(
+            Some(4),
+            Some(1),
+            TermFit::None,
+            false,
+        ));
+        let mut buffer = Vec::new();
+        option.new_unicode_art(&image)?.write_all(&mut buffer)?;
+        assert_eq!("🟨  \n", String::from_utf8(buffer).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_emoji() -> Result<(), UnicodeArtError> {
+        // a 20x10 image in quarters, every cell covers 5x5 pixels
+        let image = RgbaImage::from_fn(20, 10, |x, y| match (x < 10, y < 5) {
+            (true, true) => Rgba([230, 40, 40, 255]),
+            (false, true) => Rgba([40, 170, 70, 255]),
+            (true, false) => Rgba([30, 120, 220, 255]),
+            (false, false) => Rgba([250, 250, 250, 255]),
+        });
+        let image = DynamicImage::ImageRgba8(image);
+        let option = EmojiUnicodeArtOption::new(8)
+            .with_aspect_ratio(SimpleAspectRatio::new_auto_height(8, TermFit::None, false));
+        let mut buffer = Vec::new();
+        option.new_unicode_art(&image)?.write_all(&mut buffer)?;
+        let actual = std::str::from_utf8(&buffer).unwrap();
+        assert_eq!(
+            "🟥🟥💚💚\n\
+             🟦🟦⬜⬜\n",
+            actual
+        );
+        Ok(())
+    }
+}
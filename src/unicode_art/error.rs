@@ -1,12 +1,27 @@
-use std::io;
+use std::{fmt, io};
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum UnicodeArtError {
     UnsupportError,
     ImageError(image::ImageError),
     IoError(io::Error),
+    InvalidArgumentError(String),
 }
 
+impl fmt::Display for UnicodeArtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnicodeArtError::UnsupportError => write!(f, "unsupported operation"),
+            UnicodeArtError::ImageError(err) => write!(f, "image error: {}", err),
+            UnicodeArtError::IoError(err) => write!(f, "io error: {}", err),
+            UnicodeArtError::InvalidArgumentError(msg) => write!(f, "invalid argument: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for UnicodeArtError {}
+
 impl From<image::ImageError> for UnicodeArtError {
     fn from(err: image::ImageError) -> UnicodeArtError {
         UnicodeArtError::ImageError(err)
@@ -0,0 +1,404 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{BufWriter, Cursor, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use image::{DynamicImage, ImageOutputFormat, Rgb};
+
+use super::animation::{Frame, Player};
+use super::ansiart::write_ansi_art;
+use super::asciicast::write_asciicast;
+use super::error::UnicodeArtError;
+use super::grid::{Cell, CellGrid};
+use super::raster::{rasterize, CELL_HEIGHT, CELL_WIDTH, DEFAULT_BACKGROUND, DEFAULT_FOREGROUND};
+
+/// Descriptive fields for formats that can carry them
+#[derive(Debug, Default, Clone)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub group: Option<String>,
+    /// SAUCE font name
+    pub font: Option<String>,
+}
+
+/// Encoders for rendered art. Still formats (ANSI art, HTML, SVG and PNG)
+/// show the first frame of an animation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    /// Plain text, refuses ANSI escape codes
+    Text,
+    /// Renderer output as it is, escape codes included
+    Ansi,
+    /// BBS style `.ans` file with a SAUCE record
+    AnsiArt,
+    Html,
+    Svg,
+    Png,
+    Asciicast,
+}
+
+impl FromStr for OutputFormat {
+    type Err = UnicodeArtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "ansi" => Ok(OutputFormat::Ansi),
+            "ansi-art" => Ok(OutputFormat::AnsiArt),
+            "html" => Ok(OutputFormat::Html),
+            "svg" => Ok(OutputFormat::Svg),
+            "png" => Ok(OutputFormat::Png),
+            "asciicast" => Ok(OutputFormat::Asciicast),
+            _ => Err(UnicodeArtError::UnsupportError),
+        }
+    }
+}
+
+impl OutputFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "txt" => Some(OutputFormat::Text),
+            "ans" => Some(OutputFormat::AnsiArt),
+            "html" | "htm" => Some(OutputFormat::Html),
+            "svg" => Some(OutputFormat::Svg),
+            "png" => Some(OutputFormat::Png),
+            "cast" => Some(OutputFormat::Asciicast),
+            _ => None,
+        }
+    }
+
+    /// Formats that only keep the first frame.
+    pub fn is_still(&self) -> bool {
+        matches!(
+            self,
+            OutputFormat::AnsiArt | OutputFormat::Html | OutputFormat::Svg | OutputFormat::Png
+        )
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "txt",
+            OutputFormat::Ansi => "txt",
+            OutputFormat::AnsiArt => "ans",
+            OutputFormat::Html => "html",
+            OutputFormat::Svg => "svg",
+            OutputFormat::Png => "png",
+            OutputFormat::Asciicast => "cast",
+        }
+    }
+
+    pub fn write(
+        &self,
+        frames: &[Frame<String>],
+        metadata: &Metadata,
+        writer: &mut dyn Write,
+    ) -> Result<(), UnicodeArtError> {
+        let first = || CellGrid::parse(frames.first().map_or("", |frame| &frame.content));
+        match self {
+            OutputFormat::Text if frames.iter().any(|frame| frame.content.contains('\x1B')) => {
+                Err(UnicodeArtError::InvalidArgumentError(
+                    "output contains ANSI escape codes, write to .ans or pass --keep-escapes"
+                        .to_string(),
+                ))
+            }
+            OutputFormat::Text | OutputFormat::Ansi => Player::dump(frames, writer),
+            OutputFormat::AnsiArt => write_ansi_art(&first(), metadata, writer),
+            OutputFormat::Html => Ok(writer.write_all(to_html(&first(), metadata).as_bytes())?),
+            OutputFormat::Svg => Ok(writer.write_all(to_svg(&first(), metadata).as_bytes())?),
+            OutputFormat::Png => {
+                let mut bytes = Cursor::new(Vec::new());
+                DynamicImage::ImageRgba8(rasterize(&first()))
+                    .write_to(&mut bytes, ImageOutputFormat::Png)?;
+                Ok(writer.write_all(bytes.get_ref())?)
+            }
+            OutputFormat::Asciicast => write_asciicast(frames, metadata.title.as_deref(), writer),
+        }
+    }
+}
+
+/// Write a file through a temporary file in the same directory that replaces
+/// the target only once everything was written, so readers never see a
+/// partial file. Targets that aren't regular files, like `/dev/stdout` or a
+/// named pipe, are written directly.
+pub fn write_atomic<F>(path: &Path, write: F) -> Result<(), UnicodeArtError>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), UnicodeArtError>,
+{
+    let existing = fs::metadata(path).ok();
+    if existing.as_ref().is_some_and(|metadata| !metadata.is_file()) {
+        let mut writer = BufWriter::new(fs::OpenOptions::new().write(true).open(path)?);
+        write(&mut writer)?;
+        writer.flush()?;
+        return Ok(());
+    }
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    // temporary files are private, new files are created like any other file
+    // under the umask
+    let mut builder = tempfile::Builder::new();
+    #[cfg(unix)]
+    if existing.is_none() {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(fs::Permissions::from_mode(0o666));
+    }
+    let mut file = builder.tempfile_in(dir)?;
+    {
+        let mut writer = BufWriter::new(file.as_file_mut());
+        write(&mut writer)?;
+        writer.flush()?;
+    }
+    // a replaced file keeps its permissions
+    if let Some(metadata) = existing {
+        file.as_file().set_permissions(metadata.permissions())?;
+    }
+    file.persist(path).map_err(|err| err.error)?;
+    Ok(())
+}
+
+fn hex(color: Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+fn escape_xml_str(s: &str) -> String {
+    let mut out = String::new();
+    s.chars().for_each(|ch| escape_xml(ch, &mut out));
+    out
+}
+
+fn escape_xml(ch: char, out: &mut String) {
+    match ch {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '"' => out.push_str("&quot;"),
+        _ => out.push(ch),
+    }
+}
+
+/// Consecutive cells of a row sharing colors, as (start column, cells).
+fn runs(cells: &[Cell]) -> Vec<(usize, &[Cell])> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    for i in 1..=cells.len() {
+        let same =
+            i < cells.len() && cells[i].fg == cells[start].fg && cells[i].bg == cells[start].bg;
+        if !same {
+            runs.push((start, &cells[start..i]));
+            start = i;
+        }
+    }
+    runs
+}
+
+fn to_html(grid: &CellGrid, metadata: &Metadata) -> String {
+    let title = match &metadata.title {
+        Some(title) => format!("<title>{}</title>", escape_xml_str(title)),
+        None => String::new(),
+    };
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\">{title}</head>\n\
+         <body style=\"background:{bg}\">\n<pre style=\"color:{fg};background:{bg};\
+         font-family:monospace;line-height:1\">\n",
+        title = title,
+        fg = hex(DEFAULT_FOREGROUND),
+        bg = hex(DEFAULT_BACKGROUND)
+    );
+    for cells in grid.rows().take(grid.height()) {
+        for (_, run) in runs(cells) {
+            let mut style = Vec::new();
+            if let Some(fg) = run[0].fg {
+                style.push(format!("color:{}", hex(fg)));
+            }
+            if let Some(bg) = run[0].bg {
+                style.push(format!("background:{}", hex(bg)));
+            }
+            if !style.is_empty() {
+                write!(html, "<span style=\"{}\">", style.join(";")).unwrap();
+            }
+            run.iter()
+                .filter(|cell| !cell.is_wide_tail())
+                .for_each(|cell| escape_xml(cell.ch, &mut html));
+            if !style.is_empty() {
+                html.push_str("</span>");
+            }
+        }
+        html.push('\n');
+    }
+    html.push_str("</pre>\n</body>\n</html>\n");
+    html
+}
+
+fn to_svg(grid: &CellGrid, metadata: &Metadata) -> String {
+    let (width, height) = (
+        grid.width() as u32 * CELL_WIDTH,
+        grid.height() as u32 * CELL_HEIGHT,
+    );
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+         viewBox=\"0 0 {w} {h}\" font-family=\"monospace\" font-size=\"{size}\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"{bg}\"/>\n",
+        w = width,
+        h = height,
+        size = CELL_HEIGHT - 2,
+        bg = hex(DEFAULT_BACKGROUND)
+    );
+    if let Some(title) = &metadata.title {
+        writeln!(svg, "<title>{}</title>", escape_xml_str(title)).unwrap();
+    }
+    for (row, cells) in grid.rows().take(grid.height()).enumerate() {
+        let y = row as u32 * CELL_HEIGHT;
+        for (col, run) in runs(cells) {
+            let x = col as u32 * CELL_WIDTH;
+            let run_width = run.len() as u32 * CELL_WIDTH;
+            if let Some(bg) = run[0].bg {
+                writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                    x,
+                    y,
+                    run_width,
+                    CELL_HEIGHT,
+                    hex(bg)
+                )
+                .unwrap();
+            }
+            if run.iter().all(|cell| cell.ch == ' ') {
+                continue;
+            }
+            let mut text = String::new();
+            run.iter()
+                .filter(|cell| !cell.is_wide_tail())
+                .for_each(|cell| escape_xml(cell.ch, &mut text));
+            // stretch the glyphs so that columns line up whatever the font
+            writeln!(
+                svg,
+                "<text x=\"{}\" y=\"{}\" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\" \
+                 fill=\"{}\" xml:space=\"preserve\">{}</text>",
+                x,
+                y + CELL_HEIGHT - 3,
+                run_width,
+                hex(run[0].fg.unwrap_or(DEFAULT_FOREGROUND)),
+                text
+            )
+            .unwrap();
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+    use std::time::Duration;
+
+    fn frames(content: &str) -> Vec<Frame<String>> {
+        vec![Frame::new(content.to_string(), Duration::ZERO)]
+    }
+
+    fn export(format: OutputFormat, content: &str) -> Result<Vec<u8>, UnicodeArtError> {
+        let mut buf = Vec::new();
+        format.write(&frames(content), &Metadata::default(), &mut buf)?;
+        Ok(buf)
+    }
+
+    #[test]
+    fn test_from_path() {
+        let format = |path| OutputFormat::from_path(Path::new(path));
+        assert_eq!(Some(OutputFormat::Text), format("out/art.txt"));
+        assert_eq!(Some(OutputFormat::AnsiArt), format("art.ans"));
+        assert_eq!(Some(OutputFormat::Png), format("art.PNG"));
+        assert_eq!(Some(OutputFormat::Asciicast), format("demo.cast"));
+        assert_eq!(None, format("art"));
+    }
+
+    #[test]
+    fn test_text_refuses_escapes() -> Result<(), UnicodeArtError> {
+        let colored = "\x1B[38;2;1;2;3mx\x1B[0m\n";
+        assert!(export(OutputFormat::Text, colored).is_err());
+        assert_eq!(colored.as_bytes(), export(OutputFormat::Ansi, colored)?);
+        assert_eq!(b"x\n".to_vec(), export(OutputFormat::Text, "x\n")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_html() -> Result<(), UnicodeArtError> {
+        let html = String::from_utf8(export(
+            OutputFormat::Html,
+            "<\x1B[38;2;255;0;0mab\x1B[0m&\n",
+        )?)
+        .unwrap();
+        assert!(html.contains("&lt;<span style=\"color:#ff0000\">ab</span>&amp;\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_svg() -> Result<(), UnicodeArtError> {
+        let svg =
+            String::from_utf8(export(OutputFormat::Svg, "\x1B[48;2;0;0;255m \x1B[0mx\n")?).unwrap();
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert!(svg.contains("<rect x=\"0\" y=\"0\" width=\"7\" height=\"14\" fill=\"#0000ff\"/>"));
+        assert!(svg.contains(">x</text>"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_png() -> Result<(), UnicodeArtError> {
+        let png = export(OutputFormat::Png, "ab\ncd\n")?;
+        let image = image::load_from_memory(&png)?;
+        assert_eq!((2 * CELL_WIDTH, 2 * CELL_HEIGHT), image.dimensions());
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_atomic() -> Result<(), UnicodeArtError> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("art.txt");
+        write_atomic(&path, |writer| Ok(writer.write_all(b"old")?))?;
+        let failed = write_atomic(&path, |writer| {
+            writer.write_all(b"new")?;
+            Err(UnicodeArtError::UnsupportError)
+        });
+        assert!(failed.is_err());
+        // the previous content survives a failed write, no temporary files remain
+        assert_eq!("old", fs::read_to_string(&path)?);
+        assert_eq!(1, fs::read_dir(dir.path())?.count());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_permissions() -> Result<(), UnicodeArtError> {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("art.txt");
+        let mode = |path: &Path| -> Result<u32, UnicodeArtError> {
+            Ok(fs::metadata(path)?.permissions().mode() & 0o777)
+        };
+        write_atomic(&path, |writer| Ok(writer.write_all(b"old")?))?;
+        // new files follow the umask
+        let plain = dir.path().join("plain.txt");
+        fs::File::create(&plain)?;
+        assert_eq!(mode(&plain)?, mode(&path)?);
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        write_atomic(&path, |writer| Ok(writer.write_all(b"new")?))?;
+        assert_eq!(0o600, mode(&path)?);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_device() -> Result<(), UnicodeArtError> {
+        use std::os::unix::fs::FileTypeExt;
+        // devices can't be replaced, nor can a temporary file be put next to them
+        write_atomic(Path::new("/dev/null"), |writer| Ok(writer.write_all(b"art")?))?;
+        assert!(fs::metadata("/dev/null")?.file_type().is_char_device());
+        Ok(())
+    }
+}
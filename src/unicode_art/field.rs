@@ -0,0 +1,113 @@
+use std::io::Write;
+
+use image::{Rgb, RgbImage, Rgba};
+
+use super::color::{AnsiColor, ANSI_DEFAULT_BACKGROUND, ANSI_RESET_ATTRIBUTES};
+use super::error::UnicodeArtError;
+use super::gradient::Gradient;
+
+/// Samples without a value, like points inside a fractal
+const INSIDE_COLOR: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+/// How the generated patterns draw values from 0 to 1.
+pub struct FieldStyle<'a> {
+    /// Characters from low to high values, `None` gets the last
+    pub charset: &'a [char],
+    /// Truecolor output, `None` is drawn black
+    pub gradient: Option<&'a Gradient>,
+    /// Two colored samples per cell with upper half blocks, grayscale
+    /// without a gradient
+    pub is_half_blocks: bool,
+}
+
+impl<'a> FieldStyle<'a> {
+    /// Samples drawn in every cell from top to bottom.
+    pub fn samples_per_row(&self) -> u32 {
+        match self.is_half_blocks {
+            true => 2,
+            false => 1,
+        }
+    }
+
+    fn to_char(&self, value: Option<f64>) -> char {
+        let last = self.charset.len() - 1;
+        let index = match value {
+            Some(value) => (value * last as f64) as usize,
+            None => last,
+        };
+        self.charset[index.min(last)]
+    }
+
+    fn to_color(gradient: &Gradient, value: Option<f64>) -> Rgba<u8> {
+        match value {
+            Some(value) => gradient.at(value),
+            None => INSIDE_COLOR,
+        }
+    }
+
+    /// Draw the values as a `width` × `height` bitmap. Without a gradient
+    /// high values are dark, so image renderers pick the same dense
+    /// characters for them as the charset does.
+    pub fn to_image(
+        &self,
+        width: u32,
+        height: u32,
+        value: impl Fn(u32, u32) -> Option<f64>,
+    ) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| {
+            let color = match (self.gradient, value(x, y)) {
+                (Some(gradient), value) => Self::to_color(gradient, value),
+                (None, Some(value)) => {
+                    let luma = ((1.0 - value.clamp(0.0, 1.0)) * 255.0).round() as u8;
+                    Rgba([luma, luma, luma, 255])
+                }
+                (None, None) => INSIDE_COLOR,
+            };
+            Rgb([color[0], color[1], color[2]])
+        })
+    }
+
+    /// Write `num_cols` × `num_rows` cells, `value(col, sample_row)` is asked
+    /// for `samples_per_row` samples in every row of cells.
+    pub fn write_field(
+        &self,
+        writer: &mut dyn Write,
+        num_cols: u32,
+        num_rows: u32,
+        value: impl Fn(u32, u32) -> Option<f64>,
+    ) -> Result<(), UnicodeArtError> {
+        let grayscale = Gradient::grayscale();
+        let is_color = self.gradient.is_some() || self.is_half_blocks;
+        for row in 0..num_rows {
+            for col in 0..num_cols {
+                match (self.gradient, self.is_half_blocks) {
+                    (gradient, true) => {
+                        let gradient = gradient.unwrap_or(&grayscale);
+                        let upper = Self::to_color(gradient, value(col, row * 2));
+                        let lower = Self::to_color(gradient, value(col, row * 2 + 1));
+                        write!(
+                            writer,
+                            "{}{}\u{2580}", // ▀
+                            upper.foreground(),
+                            lower.background()
+                        )?;
+                    }
+                    (Some(gradient), false) => {
+                        let value = value(col, row);
+                        let color = Self::to_color(gradient, value);
+                        write!(writer, "{}{}", color.foreground(), self.to_char(value))?;
+                    }
+                    (None, false) => write!(writer, "{}", self.to_char(value(col, row)))?,
+                }
+            }
+            match is_color {
+                true => writeln!(writer, "{}", ANSI_DEFAULT_BACKGROUND)?,
+                false => writeln!(writer)?,
+            }
+        }
+        if is_color {
+            write!(writer, "{}", ANSI_RESET_ATTRIBUTES)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,660 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::str::FromStr;
+
+use image::{DynamicImage, Rgb, RgbImage};
+
+use super::aspect_ratio::DEFAULT_CELL_ASPECT;
+use super::color::{AnsiColor, ANSI_RESET_ATTRIBUTES};
+use super::gradient::Gradient;
+use super::{error::UnicodeArtError, ImageSource, PatternOption, UnicodeArt};
+
+/// Fonts built into the binary, `--font` also takes a path to a `.flf` file.
+/// They are named after the bitmap fonts they are drawn from so that they
+/// aren't taken for the standard FIGlet fonts, and they don't smush.
+pub const FONT_NAMES: [&str; 4] = ["half5x8", "half4x6", "blocks5x7", "hash5x7"];
+pub const DEFAULT_FONT: &str = "half5x8";
+/// Characters every font defines in order after the comments: printable
+/// ASCII, then the Deutsch umlauts and ß
+const REQUIRED_CHARS: [u32; 7] = [196, 214, 220, 228, 246, 252, 223];
+
+// horizontal smushing rules of the full layout
+const SMUSH_EQUAL: u32 = 1;
+const SMUSH_LOWLINE: u32 = 2;
+const SMUSH_HIERARCHY: u32 = 4;
+const SMUSH_PAIR: u32 = 8;
+const SMUSH_BIG_X: u32 = 16;
+const SMUSH_HARDBLANK: u32 = 32;
+const SMUSH_RULES: u32 = 63;
+const FULL_LAYOUT_FITTING: i64 = 64;
+const FULL_LAYOUT_SMUSHING: i64 = 128;
+
+/// Classes of the hierarchy rule, a character of a later class replaces one
+/// of an earlier class
+const HIERARCHY: [&str; 6] = ["|", "/\\", "[]", "{}", "()", "<>"];
+
+/// How far the characters of a banner move together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// Every character keeps the full width of its glyph
+    FullWidth,
+    /// Characters move together until they touch
+    Fitting,
+    /// Characters overlap by one more column where the font's smushing
+    /// rules merge the touching characters
+    Smushing,
+}
+
+impl FromStr for Layout {
+    type Err = UnicodeArtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(Layout::FullWidth),
+            "fitting" => Ok(Layout::Fitting),
+            "smushing" => Ok(Layout::Smushing),
+            _ => Err(UnicodeArtError::InvalidArgumentError(format!(
+                "invalid layout {}",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Justify {
+    Left,
+    Center,
+    Right,
+}
+
+impl FromStr for Justify {
+    type Err = UnicodeArtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(Justify::Left),
+            "center" => Ok(Justify::Center),
+            "right" => Ok(Justify::Right),
+            _ => Err(UnicodeArtError::InvalidArgumentError(format!(
+                "invalid justification {}",
+                s
+            ))),
+        }
+    }
+}
+
+type Glyph = Vec<Vec<char>>;
+
+/// A FIGlet font as described by the `flf2a` format.
+#[derive(Debug, Clone)]
+pub struct FigFont {
+    /// Drawn as a space, but never removed by fitting or smushing
+    hardblank: char,
+    height: usize,
+    /// The font's own layout, used unless another one is asked for
+    layout: Layout,
+    /// Smushing rules, 0 is universal smushing
+    smush_rules: u32,
+    glyphs: HashMap<char, Glyph>,
+}
+
+/// A code tag in decimal, hexadecimal with `0x` or octal with a leading 0.
+fn parse_code(tag: &str) -> Option<i64> {
+    let (sign, tag) = match tag.strip_prefix('-') {
+        Some(tag) => (-1, tag),
+        None => (1, tag),
+    };
+    let code = if let Some(hex) = tag.strip_prefix("0x").or_else(|| tag.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if tag.len() > 1 && tag.starts_with('0') {
+        i64::from_str_radix(&tag[1..], 8).ok()?
+    } else {
+        tag.parse().ok()?
+    };
+    Some(sign * code)
+}
+
+/// The `height` lines of a character, without their endmarks.
+fn read_glyph<'a>(lines: &mut impl Iterator<Item = &'a str>, height: usize) -> Option<Glyph> {
+    let mut glyph: Glyph = Vec::with_capacity(height);
+    for _ in 0..height {
+        let line = lines.next()?.trim_end();
+        let endmark = line.chars().last()?;
+        glyph.push(line.trim_end_matches(endmark).chars().collect());
+    }
+    let width = glyph.iter().map(Vec::len).max().unwrap_or(0);
+    for row in glyph.iter_mut() {
+        row.resize(width, ' ');
+    }
+    Some(glyph)
+}
+
+impl FigFont {
+    pub fn parse(data: &str) -> Result<Self, UnicodeArtError> {
+        let invalid = |msg: &str| {
+            UnicodeArtError::InvalidArgumentError(format!("invalid FIGlet font: {}", msg))
+        };
+        let mut lines = data.lines();
+        let header = lines
+            .next()
+            .and_then(|header| header.strip_prefix("flf2a"))
+            .ok_or_else(|| invalid("missing flf2a signature"))?;
+        let mut header = header.chars();
+        let hardblank = header.next().ok_or_else(|| invalid("missing hardblank"))?;
+        let params = header
+            .as_str()
+            .split_whitespace()
+            .map(|param| param.parse::<i64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid("malformed header"))?;
+        if params.len() < 5 || params[0] < 1 || params[4] < 0 {
+            return Err(invalid("malformed header"));
+        }
+        let height = params[0] as usize;
+        let old_layout = params[3];
+        let (layout, smush_rules) = match params.get(6) {
+            Some(&full) if full & FULL_LAYOUT_SMUSHING != 0 => (Layout::Smushing, full),
+            Some(&full) if full & FULL_LAYOUT_FITTING != 0 => (Layout::Fitting, full),
+            Some(&full) => (Layout::FullWidth, full),
+            None if old_layout < 0 => (Layout::FullWidth, 0),
+            None if old_layout == 0 => (Layout::Fitting, 0),
+            None => (Layout::Smushing, old_layout),
+        };
+        let mut lines = lines.skip(params[4] as usize);
+
+        let mut glyphs = HashMap::new();
+        for code in (32..127).chain(REQUIRED_CHARS) {
+            let glyph = read_glyph(&mut lines, height)
+                .ok_or_else(|| invalid("missing required characters"))?;
+            glyphs.insert(char::from_u32(code).unwrap(), glyph);
+        }
+        // code tagged characters, up to the end of the file
+        while let Some(tag) = lines.next() {
+            let code = match tag.split_whitespace().next() {
+                Some(code) => parse_code(code).ok_or_else(|| invalid("malformed code tag"))?,
+                None => continue,
+            };
+            let glyph =
+                read_glyph(&mut lines, height).ok_or_else(|| invalid("truncated character"))?;
+            // negative codes are not Unicode and can't be typed
+            if let Some(ch) = u32::try_from(code).ok().and_then(char::from_u32) {
+                glyphs.insert(ch, glyph);
+            }
+        }
+        Ok(Self {
+            hardblank,
+            height,
+            layout,
+            smush_rules: smush_rules as u32 & SMUSH_RULES,
+            glyphs,
+        })
+    }
+
+    /// One of the fonts in `FONT_NAMES`.
+    pub fn named(name: &str) -> Result<Self, UnicodeArtError> {
+        let data = match name {
+            "half5x8" => include_str!("fonts/half5x8.flf"),
+            "half4x6" => include_str!("fonts/half4x6.flf"),
+            "blocks5x7" => include_str!("fonts/blocks5x7.flf"),
+            "hash5x7" => include_str!("fonts/hash5x7.flf"),
+            _ => {
+                return Err(UnicodeArtError::InvalidArgumentError(format!(
+                    "unknown font {}",
+                    name
+                )))
+            }
+        };
+        Self::parse(data)
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Merge two touching characters, `None` when they can't be smushed.
+    fn smush(&self, left: char, right: char) -> Option<char> {
+        if left == ' ' {
+            return Some(right);
+        }
+        if right == ' ' {
+            return Some(left);
+        }
+        let rules = self.smush_rules;
+        if rules == 0 {
+            // universal smushing prefers visible characters, then the later one
+            return Some(if right == self.hardblank { left } else { right });
+        }
+        if left == self.hardblank || right == self.hardblank {
+            return match rules & SMUSH_HARDBLANK != 0 && left == right {
+                true => Some(left),
+                false => None,
+            };
+        }
+        if rules & SMUSH_EQUAL != 0 && left == right {
+            return Some(left);
+        }
+        let class = |ch: char| HIERARCHY.iter().position(|class| class.contains(ch));
+        if rules & SMUSH_LOWLINE != 0 {
+            match (left, right) {
+                ('_', right) if class(right).is_some() => return Some(right),
+                (left, '_') if class(left).is_some() => return Some(left),
+                _ => {}
+            }
+        }
+        if rules & SMUSH_HIERARCHY != 0 {
+            if let (Some(left_class), Some(right_class)) = (class(left), class(right)) {
+                match left_class.cmp(&right_class) {
+                    std::cmp::Ordering::Less => return Some(right),
+                    std::cmp::Ordering::Greater => return Some(left),
+                    std::cmp::Ordering::Equal => {}
+                }
+            }
+        }
+        if rules & SMUSH_PAIR != 0 {
+            if let ('[', ']') | (']', '[') | ('{', '}') | ('}', '{') | ('(', ')') | (')', '(') =
+                (left, right)
+            {
+                return Some('|');
+            }
+        }
+        if rules & SMUSH_BIG_X != 0 {
+            match (left, right) {
+                ('/', '\\') => return Some('|'),
+                ('\\', '/') => return Some('Y'),
+                ('>', '<') => return Some('X'),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Columns `glyph` can move into the end of `line`.
+    fn overlap(&self, line: &Line, glyph: &Glyph, layout: Layout) -> usize {
+        let width = glyph.first().map_or(0, Vec::len);
+        if layout == Layout::FullWidth || line.width() == 0 {
+            return 0;
+        }
+        let can_smush = layout == Layout::Smushing && line.last_width >= 2 && width >= 2;
+        let mut overlap = width;
+        for (line_row, glyph_row) in line.rows.iter().zip(glyph) {
+            let trailing = line_row.iter().rev().take_while(|&&ch| ch == ' ').count();
+            let leading = glyph_row.iter().take_while(|&&ch| ch == ' ').count();
+            let mut amount = trailing + leading;
+            if let (Some(&left), Some(&right)) =
+                (line_row.iter().rev().nth(trailing), glyph_row.get(leading))
+            {
+                if can_smush && self.smush(left, right).is_some() {
+                    amount += 1;
+                }
+            }
+            overlap = overlap.min(amount);
+        }
+        overlap
+    }
+
+    /// Add a character to the end of a line of the banner, unknown
+    /// characters are left out.
+    fn append(&self, line: &mut Line, ch: char, layout: Layout) {
+        let glyph = match self.glyphs.get(&ch) {
+            Some(glyph) => glyph,
+            None => return,
+        };
+        let overlap = self.overlap(line, glyph, layout);
+        for (line_row, glyph_row) in line.rows.iter_mut().zip(glyph) {
+            let start = line_row.len().saturating_sub(overlap);
+            let skipped = overlap - (line_row.len() - start);
+            for (col, &right) in glyph_row.iter().enumerate().take(overlap).skip(skipped) {
+                let left = &mut line_row[start + col - skipped];
+                *left = self.smush(*left, right).unwrap_or(right);
+            }
+            line_row.extend(glyph_row.iter().skip(overlap));
+        }
+        line.last_width = glyph.first().map_or(0, Vec::len);
+    }
+
+    /// Lay out a line of text, wrapped at spaces so that every line of the
+    /// banner fits in `width` columns. Words wider than that are broken
+    /// between characters.
+    fn wrap(&self, text: &str, layout: Layout, width: usize) -> Vec<Line> {
+        let mut lines = Vec::new();
+        let mut line = Line::new(self.height);
+        for word in text.split(' ') {
+            let mut next = line.clone();
+            if line.width() > 0 {
+                self.append(&mut next, ' ', layout);
+            }
+            for ch in word.chars() {
+                self.append(&mut next, ch, layout);
+            }
+            if next.width() <= width {
+                line = next;
+                continue;
+            }
+            if line.width() > 0 {
+                lines.push(line);
+            }
+            line = Line::new(self.height);
+            for ch in word.chars() {
+                let mut next = line.clone();
+                self.append(&mut next, ch, layout);
+                if next.width() > width && line.width() > 0 {
+                    lines.push(line);
+                    line = Line::new(self.height);
+                    self.append(&mut line, ch, layout);
+                } else {
+                    line = next;
+                }
+            }
+        }
+        lines.push(line);
+        lines
+    }
+
+    /// Rows of the banner for `text`, with hardblanks drawn as spaces.
+    pub fn render(
+        &self,
+        text: &str,
+        layout: Layout,
+        width: usize,
+        justify: Justify,
+    ) -> Vec<String> {
+        let mut rows = Vec::new();
+        for text in text.lines() {
+            for line in self.wrap(text, layout, width) {
+                let padding = match justify {
+                    Justify::Left => 0,
+                    Justify::Center => width.saturating_sub(line.width()) / 2,
+                    Justify::Right => width.saturating_sub(line.width()),
+                };
+                rows.extend(line.rows.iter().map(|row| {
+                    let row: String = row
+                        .iter()
+                        .map(|&ch| if ch == self.hardblank { ' ' } else { ch })
+                        .collect();
+                    format!("{}{}", " ".repeat(padding), row)
+                        .trim_end()
+                        .to_string()
+                }));
+            }
+        }
+        rows
+    }
+}
+
+/// A line of the banner being laid out.
+#[derive(Clone)]
+struct Line {
+    rows: Vec<Vec<char>>,
+    /// Width of the last glyph, narrow glyphs are never smushed
+    last_width: usize,
+}
+
+impl Line {
+    fn new(height: usize) -> Self {
+        Self {
+            rows: vec![Vec::new(); height],
+            last_width: 0,
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.rows.first().map_or(0, Vec::len)
+    }
+}
+
+/// Text drawn in large letters made of characters.
+#[derive(Clone)]
+pub struct FigletAsciiArtOption {
+    font: FigFont,
+    text: String,
+    num_cols: u32,
+    layout: Layout,
+    justify: Justify,
+    gradient: Option<Gradient>,
+}
+
+impl FigletAsciiArtOption {
+    /// Banner lines wrap to fit in `num_cols`.
+    pub fn new(font: FigFont, text: &str, num_cols: u32) -> Self {
+        Self {
+            layout: font.layout(),
+            font,
+            text: text.to_string(),
+            num_cols,
+            justify: Justify::Left,
+            gradient: None,
+        }
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_justify(mut self, justify: Justify) -> Self {
+        self.justify = justify;
+        self
+    }
+
+    /// Truecolor characters, the gradient runs from the left to the right.
+    pub fn with_gradient(mut self, gradient: Gradient) -> Self {
+        self.gradient = Some(gradient);
+        self
+    }
+
+    fn rows(&self) -> Vec<String> {
+        self.font.render(
+            &self.text,
+            self.layout,
+            self.num_cols as usize,
+            self.justify,
+        )
+    }
+
+    fn color_at(&self, col: usize) -> Option<image::Rgba<u8>> {
+        let last = (self.num_cols.max(2) - 1) as f64;
+        self.gradient
+            .as_ref()
+            .map(|gradient| gradient.at(col as f64 / last))
+    }
+}
+
+impl ImageSource for FigletAsciiArtOption {
+    /// Ink on white, or the gradient on white. Half blocks only ink their
+    /// half of the cell.
+    fn to_image(&self, width: u32) -> Result<DynamicImage, UnicodeArtError> {
+        let rows: Vec<Vec<char>> = self
+            .rows()
+            .iter()
+            .map(|row| row.chars().collect())
+            .collect();
+        let num_cols = self.num_cols as usize;
+        let cell_width = width as f64 / num_cols as f64;
+        // as tall as the cells the renderers assume
+        let cell_height = cell_width / DEFAULT_CELL_ASPECT;
+        let height = ((rows.len() as f64 * cell_height).round() as u32).max(1);
+        let cell_height = height as f64 / rows.len().max(1) as f64;
+        let image = RgbImage::from_fn(width, height, |x, y| {
+            let col = (x as f64 / cell_width) as usize;
+            let (row, fraction) = {
+                let row = y as f64 / cell_height;
+                (row as usize, row.fract())
+            };
+            let ch = rows
+                .get(row)
+                .and_then(|row| row.get(col))
+                .copied()
+                .unwrap_or(' ');
+            let is_ink = match ch {
+                ' ' => false,
+                '\u{2580}' => fraction < 0.5,  // ▀
+                '\u{2584}' => fraction >= 0.5, // ▄
+                _ => true,
+            };
+            match (is_ink, self.color_at(col)) {
+                (false, _) => Rgb([255, 255, 255]),
+                (true, Some(color)) => Rgb([color[0], color[1], color[2]]),
+                (true, None) => Rgb([0, 0, 0]),
+            }
+        });
+        Ok(DynamicImage::ImageRgb8(image))
+    }
+}
+
+impl PatternOption for FigletAsciiArtOption {
+    fn new_pattern_art(&self) -> Result<Box<dyn UnicodeArt + '_>, UnicodeArtError> {
+        Ok(Box::new(FigletAsciiArt { option: self }))
+    }
+}
+
+pub struct FigletAsciiArt<'a> {
+    option: &'a FigletAsciiArtOption,
+}
+
+impl<'a> UnicodeArt for FigletAsciiArt<'a> {
+    fn write_all(&self, writer: &mut dyn Write) -> Result<(), UnicodeArtError> {
+        for row in self.option.rows() {
+            for (col, ch) in row.chars().enumerate() {
+                match self.option.color_at(col) {
+                    Some(color) if ch != ' ' => write!(writer, "{}{}", color.foreground(), ch)?,
+                    _ => write!(writer, "{}", ch)?,
+                }
+            }
+            writeln!(writer)?;
+        }
+        if self.option.gradient.is_some() {
+            write!(writer, "{}", ANSI_RESET_ATTRIBUTES)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+
+    /// Every printable character is blank except for the few the tests use,
+    /// full layout 128 + 63 smushes with every rule.
+    fn test_font(full_layout: i64) -> FigFont {
+        let glyphs: HashMap<char, [&str; 2]> = HashMap::from([
+            (' ', ["$ ", "$ "]),
+            ('/', ["  /", " / "]),
+            ('\\', ["\\  ", " \\ "]),
+            ('H', ["|  |", "|--|"]),
+            ('I', ["|", "|"]),
+            ('_', ["  ", "__"]),
+            ('[', [" [", " ["]),
+            (']', ["] ", "] "]),
+        ]);
+        let mut data = format!(
+            "flf2a$ 2 2 4 -1 1 0 {} 1\nsmushing test font\n",
+            full_layout
+        );
+        for code in (32..127).chain(REQUIRED_CHARS) {
+            let ch = char::from_u32(code).unwrap();
+            let [top, bottom] = glyphs.get(&ch).copied().unwrap_or(["", ""]);
+            data += &format!("{}@\n{}@@\n", top, bottom);
+        }
+        data += "0x263A  WHITE SMILING FACE\n:)@\n  @@\n";
+        FigFont::parse(&data).unwrap()
+    }
+
+    fn render(font: &FigFont, text: &str, layout: Layout) -> String {
+        font.render(text, layout, 80, Justify::Left).join("\n")
+    }
+
+    #[test]
+    fn test_parse() {
+        let font = test_font(128 + 63);
+        assert_eq!(2, font.height);
+        assert_eq!(Layout::Smushing, font.layout());
+        assert_eq!(":)\n", render(&font, "\u{263A}", Layout::FullWidth));
+        for invalid in ["", "flf2a$ 2 2", "flf2a$ 2 2 4 -1 0\n@\n@@\n"] {
+            assert!(FigFont::parse(invalid).is_err(), "{:?}", invalid);
+        }
+        assert_eq!(Some(255), parse_code("0xff"));
+        assert_eq!(Some(8), parse_code("010"));
+        assert_eq!(Some(-2), parse_code("-2"));
+        for name in FONT_NAMES {
+            assert!(FigFont::named(name).is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_layouts() {
+        let font = test_font(128 + 63);
+        assert_eq!("|  ||  |\n|--||--|", render(&font, "HH", Layout::FullWidth));
+        assert_eq!("|  ||  |\n|--||--|", render(&font, "HH", Layout::Fitting));
+        // equal characters
+        assert_eq!("|  |  |\n|--|--|", render(&font, "HH", Layout::Smushing));
+        // big X
+        assert_eq!("  /\\\n /  \\", render(&font, "/\\", Layout::Fitting));
+        assert_eq!("  |\n / \\", render(&font, "/\\", Layout::Smushing));
+        assert_eq!("\\  /\n \\/", render(&font, "\\/", Layout::Fitting));
+        // opposite brackets
+        assert_eq!(" []\n []", render(&font, "[]", Layout::Fitting));
+        assert_eq!(" |\n |", render(&font, "[]", Layout::Smushing));
+        // underscore and hierarchy
+        assert_eq!(" |  |\n_|--|", render(&font, "_H", Layout::Smushing));
+        assert_eq!("]/\n]", render(&font, "]/", Layout::Smushing));
+        // narrow characters are only fitted
+        assert_eq!("||\n||", render(&font, "II", Layout::Smushing));
+        // hardblanks keep their space
+        assert_eq!("| |\n| |", render(&font, "I I", Layout::Smushing));
+    }
+
+    #[test]
+    fn test_universal_smushing() {
+        let font = test_font(128);
+        assert_eq!("|  |  |\n|--|--|", render(&font, "HH", Layout::Smushing));
+        assert_eq!(" ]\n ]", render(&font, "[]", Layout::Smushing));
+        assert_eq!("  \\\n / \\", render(&font, "/\\", Layout::Smushing));
+    }
+
+    #[test]
+    fn test_wrap_and_justify() {
+        let font = test_font(128 + 63);
+        let rows = font.render("HI HI HI", Layout::Smushing, 12, Justify::Right);
+        assert_eq!(
+            vec![
+                " |  || |  ||",
+                " |--|| |--||",
+                "       |  ||",
+                "       |--||"
+            ],
+            rows
+        );
+        let rows = font.render("HHH", Layout::Smushing, 8, Justify::Center);
+        assert_eq!(vec!["|  |  |", "|--|--|", "  |  |", "  |--|"], rows);
+        assert_eq!(
+            4,
+            font.render("H\nH", Layout::Smushing, 8, Justify::Left)
+                .len()
+        );
+    }
+
+    #[test]
+    fn test_generate_banner() -> Result<(), UnicodeArtError> {
+        let option = FigletAsciiArtOption::new(FigFont::named("hash5x7")?, "Hi!", 80);
+        let mut buffer = Vec::new();
+        option.new_pattern_art()?.write_all(&mut buffer)?;
+        let actual = std::str::from_utf8(&buffer).unwrap();
+        assert_eq!(
+            "#  #    #     #\n\
+             #  #          #\n\
+             ####   ##     #\n\
+             #  #    #     #\n\
+             #  #    #\n\
+             #  #   ###    #\n\
+             \n",
+            actual
+        );
+
+        let image = option.to_image(160)?;
+        assert_eq!((160, 28), image.dimensions());
+        assert_eq!(Rgb([0, 0, 0]), image.to_rgb8()[(1, 1)]);
+        Ok(())
+    }
+}
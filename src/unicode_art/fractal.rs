@@ -0,0 +1,479 @@
+use std::io::Write;
+
+use image::DynamicImage;
+
+use super::animation::AnimatedOption;
+use super::aspect_ratio::{AspectRatio, SimpleAspectRatio, TermFit};
+use super::field::FieldStyle;
+use super::gradient::Gradient;
+use super::{error::UnicodeArtError, ImageSource, PatternOption, UnicodeArt};
+
+/// The character ramp of the original one-liner, `n + 31` for n escape steps
+pub const CHARSET_ASCII: &str = " !\"#$%&'()*+,-./0123456789:";
+pub const CHARSET_DENSITY: &str = " .:-=+*#%@";
+pub const CHARSET_BLOCKS: &str = " ░▒▓█";
+
+pub const DEFAULT_ZOOM: f64 = 1.0;
+pub const DEFAULT_ITERATIONS: u32 = 26;
+/// A Julia set that looks like a dendrite of spirals
+pub const DEFAULT_JULIA_C: (f64, f64) = (-0.8, 0.156);
+/// Size of the complex plane shown at zoom 1, -2..1 × -1..1 for the
+/// Mandelbrot set
+const VIEW_WIDTH: f64 = 3.0;
+const VIEW_HEIGHT: f64 = 2.0;
+/// Zoom sequences double the magnification every second
+const ZOOM_PER_SECOND: f64 = 2.0;
+const ITERATIONS_PER_SECOND: f64 = 8.0;
+/// A large escape radius keeps the smooth counts continuous
+const BAILOUT_SQUARED: f64 = 256.0 * 256.0;
+/// Newton's method stops this close to a root
+const NEWTON_EPSILON: f64 = 1e-6;
+const CUBE_ROOTS_OF_UNITY: [(f64, f64); 3] = [
+    (1.0, 0.0),
+    (-0.5, 0.866_025_403_784_438_6),
+    (-0.5, -0.866_025_403_784_438_6),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fractal {
+    /// z = z² + c
+    Mandelbrot,
+    /// z = z² + c with a fixed c, starting from the point
+    Julia((f64, f64)),
+    /// z = (|re z| + i |im z|)² + c
+    BurningShip,
+    /// The Mandelbar set, z = conj(z)² + c
+    Tricorn,
+    /// Newton's method for z³ - 1
+    Newton,
+}
+
+impl Fractal {
+    /// The middle of the view that shows the whole fractal at zoom 1.
+    pub fn default_center(&self) -> (f64, f64) {
+        match self {
+            Fractal::Mandelbrot => (-0.5, 0.0),
+            Fractal::BurningShip => (-0.5, -0.5),
+            Fractal::Tricorn => (-0.25, 0.0),
+            _ => (0.0, 0.0),
+        }
+    }
+
+    /// A point on the edge of the set worth zooming into, the middle of the
+    /// view for the sets without a well known one.
+    pub fn zoom_target(&self) -> (f64, f64) {
+        match self {
+            // seahorse valley
+            Fractal::Mandelbrot => (-0.743_643_887_037_151, 0.131_825_904_205_330),
+            _ => self.default_center(),
+        }
+    }
+
+    /// Fractional number of iterations until the point escapes, or converges
+    /// for Newton, `None` when it doesn't within `iterations`. Unlike whole
+    /// iteration counts, neighbouring points get close values, so mapping
+    /// counts to characters or colours doesn't show bands.
+    pub fn smooth_count(&self, (re, im): (f64, f64), iterations: u32) -> Option<f64> {
+        if *self == Fractal::Newton {
+            return newton(re, im, iterations);
+        }
+        let (cr, ci) = match *self {
+            Fractal::Julia(c) => c,
+            _ => (re, im),
+        };
+        let (mut zr, mut zi) = (re, im);
+        for n in 0..iterations {
+            let norm = zr * zr + zi * zi;
+            if norm > BAILOUT_SQUARED {
+                // log2(ln |z|) grows by one per iteration once z is large
+                let smooth = n as f64 + 1.0 - (norm.ln() / 2.0).log2();
+                return Some(smooth.clamp(0.0, iterations as f64));
+            }
+            (zr, zi) = match *self {
+                Fractal::BurningShip => (zr.abs(), zi.abs()),
+                Fractal::Tricorn => (zr, -zi),
+                _ => (zr, zi),
+            };
+            (zr, zi) = (zr * zr - zi * zi + cr, 2.0 * zr * zi + ci);
+        }
+        None
+    }
+}
+
+/// Steps of Newton's method for z³ - 1, interpolated between the last two
+/// distances to the root it converges to.
+fn newton(re: f64, im: f64, iterations: u32) -> Option<f64> {
+    let distance = |zr: f64, zi: f64| {
+        CUBE_ROOTS_OF_UNITY
+            .iter()
+            .map(|(rr, ri)| ((zr - rr).powi(2) + (zi - ri).powi(2)).sqrt())
+            .fold(f64::INFINITY, f64::min)
+    };
+    let (mut zr, mut zi) = (re, im);
+    let mut previous = distance(zr, zi);
+    for n in 0..iterations {
+        if previous < NEWTON_EPSILON {
+            return Some(n as f64);
+        }
+        // z - (z³ - 1) / 3z²
+        let (z2r, z2i) = (zr * zr - zi * zi, 2.0 * zr * zi);
+        let (z3r, z3i) = (z2r * zr - z2i * zi, z2r * zi + z2i * zr);
+        let (dr, di) = (3.0 * z2r, 3.0 * z2i);
+        let norm = dr * dr + di * di;
+        if norm == 0.0 {
+            return None;
+        }
+        let (nr, ni) = (z3r - 1.0, z3i);
+        zr -= (nr * dr + ni * di) / norm;
+        zi -= (ni * dr - nr * di) / norm;
+        let current = distance(zr, zi);
+        if current < NEWTON_EPSILON {
+            let t = (NEWTON_EPSILON.ln() - previous.ln()) / (current.ln() - previous.ln());
+            return Some(n as f64 + t.clamp(0.0, 1.0));
+        }
+        previous = current;
+    }
+    None
+}
+
+#[derive(Clone)]
+pub struct FractalAsciiArtOption {
+    fractal: Fractal,
+    center: (f64, f64),
+    zoom: f64,
+    iterations: u32,
+    charset: Vec<char>,
+    gradient: Option<Gradient>,
+    is_half_blocks: bool,
+    aspect_ratio: SimpleAspectRatio,
+    time: f64,
+}
+
+pub struct FractalAsciiArt<'a> {
+    options: &'a FractalAsciiArtOption,
+}
+
+impl FractalAsciiArtOption {
+    pub fn new(fractal: Fractal, num_cols: u32) -> Self {
+        Self {
+            fractal,
+            center: fractal.default_center(),
+            zoom: DEFAULT_ZOOM,
+            iterations: DEFAULT_ITERATIONS,
+            charset: CHARSET_ASCII.chars().collect(),
+            gradient: None,
+            is_half_blocks: false,
+            aspect_ratio: SimpleAspectRatio::new_auto_height(num_cols, TermFit::None, false),
+            time: 0.0,
+        }
+    }
+
+    pub fn with_aspect_ratio(mut self, aspect_ratio: SimpleAspectRatio) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    /// Point of the complex plane in the middle of the output.
+    pub fn with_center(mut self, center: (f64, f64)) -> Self {
+        self.center = center;
+        self
+    }
+
+    /// Magnification, zoom 1 shows the whole set.
+    pub fn with_zoom(mut self, zoom: f64) -> Self {
+        self.zoom = zoom;
+        self
+    }
+
+    pub fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations.max(1);
+        self
+    }
+
+    /// Characters from quick escape to inside the set.
+    pub fn with_charset(mut self, charset: &str) -> Self {
+        self.charset = charset.chars().collect();
+        self
+    }
+
+    /// Truecolor output, iteration counts from 0 to `iterations` are mapped
+    /// onto the gradient.
+    pub fn with_gradient(mut self, gradient: Gradient) -> Self {
+        self.gradient = Some(gradient);
+        self
+    }
+
+    /// Draw upper half blocks in two colors instead of characters, which
+    /// doubles the vertical resolution. Grayscale without a gradient.
+    pub fn with_half_blocks(mut self, is_half_blocks: bool) -> Self {
+        self.is_half_blocks = is_half_blocks;
+        self
+    }
+
+    /// Zoom into the center for `time` seconds, with more iterations as
+    /// the detail gets finer.
+    pub fn with_time(mut self, time: f64) -> Self {
+        self.time = time;
+        self
+    }
+
+    fn zoom_at_time(&self) -> f64 {
+        self.zoom * ZOOM_PER_SECOND.powf(self.time)
+    }
+
+    fn iterations_at_time(&self) -> u32 {
+        self.iterations + (self.time * ITERATIONS_PER_SECOND) as u32
+    }
+
+    fn style(&self) -> FieldStyle<'_> {
+        FieldStyle {
+            charset: &self.charset,
+            gradient: self.gradient.as_ref(),
+            is_half_blocks: self.is_half_blocks,
+        }
+    }
+
+    /// Values of the middle of every sample of a `width` × `height` grid over
+    /// the view.
+    fn sampler(&self, width: u32, height: u32) -> impl Fn(u32, u32) -> Option<f64> + '_ {
+        let (zoom, iterations) = (self.zoom_at_time(), self.iterations_at_time());
+        let (view_width, view_height) = (VIEW_WIDTH / zoom, VIEW_HEIGHT / zoom);
+        let step_re = view_width / width as f64;
+        let step_im = view_height / height as f64;
+        move |x, y| {
+            let re = self.center.0 - view_width / 2.0 + (x as f64 + 0.5) * step_re;
+            let im = self.center.1 - view_height / 2.0 + (y as f64 + 0.5) * step_im;
+            let count = self.fractal.smooth_count((re, im), iterations)?;
+            Some(count / iterations as f64)
+        }
+    }
+}
+
+impl AnimatedOption for FractalAsciiArtOption {
+    fn at_time(&self, t: f64) -> Box<dyn PatternOption> {
+        Box::new(self.clone().with_time(self.time + t))
+    }
+}
+
+impl ImageSource for FractalAsciiArtOption {
+    fn to_image(&self, width: u32) -> Result<DynamicImage, UnicodeArtError> {
+        let height = ((width as f64 * VIEW_HEIGHT / VIEW_WIDTH).round() as u32).max(1);
+        let image = self
+            .style()
+            .to_image(width, height, self.sampler(width, height));
+        Ok(DynamicImage::ImageRgb8(image))
+    }
+}
+
+impl PatternOption for FractalAsciiArtOption {
+    fn new_pattern_art(&self) -> Result<Box<dyn UnicodeArt + '_>, UnicodeArtError> {
+        if self.charset.len() < 2 {
+            return Err(UnicodeArtError::InvalidArgumentError(
+                "charset needs at least 2 characters".to_string(),
+            ));
+        }
+        Ok(Box::new(FractalAsciiArt { options: self }))
+    }
+}
+
+impl<'a> UnicodeArt for FractalAsciiArt<'a> {
+    /**
+     * Started as a port of this C one-liner, with the window, iteration
+     * count and formula as parameters:
+     *
+     * #include <stdio.h>
+     * main(n)
+     * {
+     *     float r, i, R, I, b;
+     *     for (i = -1; i < 1; i += .06, puts(""))
+     *         for (r = -2; I = i, (R = r) < 1; r += .03, putchar(n + 31))
+     *             for (n = 0; b = I * I, 26 > n++ && R * R + b < 4;
+     *                  I = 2 * R * I + i, R = R * R - b + r)
+     *                 ;
+     * }
+     * }
+     */
+    fn write_all(&self, writer: &mut dyn Write) -> Result<(), UnicodeArtError> {
+        let options = self.options;
+        // the view is sized like an image so that cells keep the plane square
+        let (num_cols, num_rows) = options
+            .aspect_ratio
+            .calculate((VIEW_WIDTH * 1000.0) as u32, (VIEW_HEIGHT * 1000.0) as u32);
+        let style = options.style();
+        // the middle of every cell, or of every half cell
+        let sampler = options.sampler(num_cols, num_rows * style.samples_per_row());
+        style.write_field(writer, num_cols, num_rows, sampler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::grid::CellGrid;
+    use super::*;
+    use image::Rgb;
+    use pretty_assertions::assert_eq;
+
+    fn render(option: &FractalAsciiArtOption) -> Result<String, UnicodeArtError> {
+        let mut buf = Vec::new();
+        option.new_pattern_art()?.write_all(&mut buf)?;
+        Ok(String::from_utf8(buf).unwrap())
+    }
+
+    #[test]
+    fn test_generate_madel() -> Result<(), UnicodeArtError> {
+        let aspect_ratio = SimpleAspectRatio::new_auto_height(40, TermFit::None, false);
+        let option =
+            FractalAsciiArtOption::new(Fractal::Mandelbrot, 40).with_aspect_ratio(aspect_ratio);
+        let actual = render(&option)?;
+        assert_eq!(
+            r########"!!!"""""""""""#####$$$%'*/'%$####""""""!
+!"""""""""######$$%%%&(5::2'%%$$###"""""
+""""""######$$$$%&::0::::::::).:%$##""""
+""""####$%%%%%%&&::::::::::::::)&$##""""
+""####$$%'.-+60)*:::::::::::::::)%$##"""
+##$$$%%'(*::::::3:::::::::::::::&$###"""
+::::::::::::::::::::::::::::::.'%$###"""
+##$$$%%'(*::::::3:::::::::::::::&$###"""
+""####$$%'.-+60)*:::::::::::::::)%$##"""
+""""####$%%%%%%&&::::::::::::::)&$##""""
+""""""######$$$$%&::0::::::::).:%$##""""
+!"""""""""######$$%%%&(5::2'%%$$###"""""
+!!!"""""""""""#####$$$%'*/'%$####""""""!
+"########,
+            actual
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_view() -> Result<(), UnicodeArtError> {
+        let aspect_ratio = SimpleAspectRatio::new(Some(16), Some(6), TermFit::None, false);
+        let option = FractalAsciiArtOption::new(Fractal::Mandelbrot, 16)
+            .with_aspect_ratio(aspect_ratio)
+            .with_center((-0.745, 0.11))
+            .with_zoom(40.0)
+            .with_iterations(100)
+            .with_charset(CHARSET_BLOCKS);
+        let actual = render(&option)?;
+        assert_eq!(
+            "█████░░█████████\n\
+             ████░░░▒████████\n\
+             ██▒░░░░█▓███████\n\
+             █▓▒░░░░█████████\n\
+             ▓▒░   ░▓▒███████\n\
+             ░░     ░▒███████\n",
+            actual
+        );
+        assert!(render(&option.with_charset("x")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_newton() -> Result<(), UnicodeArtError> {
+        let aspect_ratio = SimpleAspectRatio::new(Some(24), Some(8), TermFit::None, false);
+        let option = FractalAsciiArtOption::new(Fractal::Newton, 24)
+            .with_aspect_ratio(aspect_ratio)
+            .with_iterations(8)
+            .with_charset(CHARSET_DENSITY);
+        let actual = render(&option)?;
+        assert_eq!(
+            "++===--::-==+*@@#@%**+++\n\
+             *+++==---==+%+@@@*++++=+\n\
+             ##***+++++*%@++@#+======\n\
+             @%@@@@#@#@@@@@@*+=-----=\n\
+             @%@@@@#@#@@@@@@*+=-----=\n\
+             ##***+++++*%@++@#+======\n\
+             *+++==---==+%+@@@*++++=+\n\
+             ++===--::-==+*@@#@%**+++\n",
+            actual
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_gradient_half_blocks() -> Result<(), UnicodeArtError> {
+        let aspect_ratio = SimpleAspectRatio::new(Some(20), Some(10), TermFit::None, false);
+        let option = FractalAsciiArtOption::new(Fractal::Mandelbrot, 20)
+            .with_aspect_ratio(aspect_ratio)
+            .with_gradient(Gradient::fire())
+            .with_half_blocks(true);
+        let grid = CellGrid::parse(&render(&option)?);
+        assert_eq!((20, 10), (grid.width(), grid.height()));
+        // two points of the set's interior in one cell, escaping points outside
+        let inside = grid.get(5, 12);
+        assert_eq!(
+            ('▀', Some(Rgb([0, 0, 0])), Some(Rgb([0, 0, 0]))),
+            (inside.ch, inside.fg, inside.bg)
+        );
+        assert_ne!(Some(Rgb([0, 0, 0])), grid.get(0, 0).fg);
+
+        let option = FractalAsciiArtOption::new(Fractal::Mandelbrot, 20)
+            .with_aspect_ratio(aspect_ratio)
+            .with_gradient(Gradient::fire());
+        let grid = CellGrid::parse(&render(&option)?);
+        assert_eq!((20, 10), (grid.width(), grid.height()));
+        assert_eq!(None, grid.get(5, 12).bg);
+        assert!(grid.get(0, 0).fg.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_image() -> Result<(), UnicodeArtError> {
+        let image = FractalAsciiArtOption::new(Fractal::Mandelbrot, 40)
+            .to_image(30)?
+            .to_rgb8();
+        assert_eq!((30, 20), image.dimensions());
+        // inside the main cardioid is dark like the densest character, the
+        // corner escapes at once
+        assert_eq!(&Rgb([0, 0, 0]), image.get_pixel(17, 10));
+        assert!(image.get_pixel(0, 0)[0] > 200);
+
+        let image = FractalAsciiArtOption::new(Fractal::Mandelbrot, 40)
+            .with_gradient(Gradient::fire())
+            .to_image(30)?
+            .to_rgb8();
+        assert_eq!(&Rgb([0, 0, 0]), image.get_pixel(17, 10));
+        assert!(image.get_pixel(0, 0)[0] < 50);
+        Ok(())
+    }
+
+    #[test]
+    fn test_smooth_count() {
+        for fractal in [
+            Fractal::Mandelbrot,
+            Fractal::Julia((0.0, 0.0)),
+            Fractal::BurningShip,
+            Fractal::Tricorn,
+        ] {
+            // the origin never escapes, far away points escape at once
+            assert_eq!(None, fractal.smooth_count((0.0, 0.0), 50));
+            assert_eq!(Some(0.0), fractal.smooth_count((1000.0, 0.0), 50));
+            // counts change gradually between neighbours
+            let a = fractal.smooth_count((1.0, 0.5), 50).unwrap();
+            let b = fractal.smooth_count((1.0, 0.501), 50).unwrap();
+            assert!(a.fract() != 0.0 && (a - b).abs() < 0.1, "{:?}", fractal);
+        }
+        // the Julia set for c = -1 contains 0, the Mandelbrot set contains -1
+        assert_eq!(
+            None,
+            Fractal::Julia((-1.0, 0.0)).smooth_count((0.0, 0.0), 50)
+        );
+        assert_eq!(None, Fractal::Mandelbrot.smooth_count((-1.0, 0.0), 50));
+        // the Burning Ship folds into the first quadrant, the Tricorn conjugates
+        assert_ne!(
+            Fractal::BurningShip.smooth_count((-1.8, -0.05), 50),
+            Fractal::Mandelbrot.smooth_count((-1.8, -0.05), 50)
+        );
+        assert_eq!(
+            Fractal::Tricorn.smooth_count((0.3, 0.5), 50),
+            Fractal::Tricorn.smooth_count((0.3, -0.5), 50)
+        );
+
+        assert_eq!(Some(0.0), Fractal::Newton.smooth_count((1.0, 0.0), 50));
+        let near = Fractal::Newton.smooth_count((1.1, 0.1), 50).unwrap();
+        let far = Fractal::Newton.smooth_count((3.0, 2.0), 50).unwrap();
+        assert!(near < far);
+        // z' = 0 at the origin
+        assert_eq!(None, Fractal::Newton.smooth_count((0.0, 0.0), 50));
+    }
+}
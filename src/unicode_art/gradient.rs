@@ -0,0 +1,171 @@
+use std::str::FromStr;
+
+use image::Rgba;
+
+use super::color::parse_color;
+use super::error::UnicodeArtError;
+
+// evenly spaced samples of the matplotlib colormaps
+const VIRIDIS: [[u8; 3]; 9] = [
+    [0x44, 0x01, 0x54],
+    [0x47, 0x2d, 0x7b],
+    [0x3b, 0x52, 0x8b],
+    [0x2c, 0x72, 0x8e],
+    [0x21, 0x91, 0x8c],
+    [0x28, 0xae, 0x80],
+    [0x5e, 0xc9, 0x62],
+    [0xad, 0xdc, 0x30],
+    [0xfd, 0xe7, 0x25],
+];
+const MAGMA: [[u8; 3]; 9] = [
+    [0x00, 0x00, 0x04],
+    [0x1c, 0x10, 0x44],
+    [0x4f, 0x12, 0x7b],
+    [0x81, 0x25, 0x81],
+    [0xb5, 0x36, 0x7a],
+    [0xe5, 0x50, 0x64],
+    [0xfb, 0x87, 0x61],
+    [0xfe, 0xc2, 0x87],
+    [0xfc, 0xfd, 0xbf],
+];
+const FIRE: [(f64, [u8; 3]); 5] = [
+    (0.0, [0x00, 0x00, 0x00]),
+    (0.35, [0xc0, 0x00, 0x00]),
+    (0.6, [0xff, 0x80, 0x00]),
+    (0.85, [0xff, 0xff, 0x00]),
+    (1.0, [0xff, 0xff, 0xff]),
+];
+const GRAYSCALE: [[u8; 3]; 2] = [[0x00, 0x00, 0x00], [0xff, 0xff, 0xff]];
+
+/// Colors interpolated linearly between stops at positions from 0 to 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    stops: Vec<(f64, Rgba<u8>)>,
+}
+
+fn rgba([r, g, b]: [u8; 3]) -> Rgba<u8> {
+    Rgba([r, g, b, 255])
+}
+
+impl Gradient {
+    /// Stops spread evenly from 0 to 1.
+    pub fn new(colors: &[Rgba<u8>]) -> Self {
+        let last = colors.len().saturating_sub(1).max(1) as f64;
+        Self {
+            stops: colors
+                .iter()
+                .enumerate()
+                .map(|(i, &color)| (i as f64 / last, color))
+                .collect(),
+        }
+    }
+
+    pub fn viridis() -> Self {
+        Self::new(&VIRIDIS.map(rgba))
+    }
+
+    pub fn magma() -> Self {
+        Self::new(&MAGMA.map(rgba))
+    }
+
+    pub fn fire() -> Self {
+        Self {
+            stops: FIRE.iter().map(|&(t, color)| (t, rgba(color))).collect(),
+        }
+    }
+
+    pub fn grayscale() -> Self {
+        Self::new(&GRAYSCALE.map(rgba))
+    }
+
+    /// The color at `t`, clamped to 0..=1.
+    pub fn at(&self, t: f64) -> Rgba<u8> {
+        let t = t.clamp(0.0, 1.0);
+        let upper = self
+            .stops
+            .iter()
+            .position(|&(position, _)| position >= t)
+            .unwrap_or(self.stops.len() - 1);
+        if upper == 0 {
+            return self.stops[0].1;
+        }
+        let (from, from_color) = self.stops[upper - 1];
+        let (to, to_color) = self.stops[upper];
+        let amount = match to - from {
+            span if span > 0.0 => (t - from) / span,
+            _ => 1.0,
+        };
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * amount).round() as u8;
+        Rgba([
+            mix(from_color[0], to_color[0]),
+            mix(from_color[1], to_color[1]),
+            mix(from_color[2], to_color[2]),
+            255,
+        ])
+    }
+}
+
+impl FromStr for Gradient {
+    type Err = UnicodeArtError;
+
+    /// A named gradient, or stops such as `#000,#f00@0.3,#fff` where stops
+    /// without a position are spread evenly.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viridis" => return Ok(Self::viridis()),
+            "magma" => return Ok(Self::magma()),
+            "fire" => return Ok(Self::fire()),
+            "grayscale" => return Ok(Self::grayscale()),
+            _ => {}
+        }
+        let invalid = || UnicodeArtError::InvalidArgumentError(format!("invalid gradient {}", s));
+        let stops = s
+            .split(',')
+            .map(|stop| {
+                let (color, position) = match stop.split_once('@') {
+                    Some((color, position)) => (color, Some(position.trim().parse::<f64>().ok()?)),
+                    None => (stop, None),
+                };
+                Some((parse_color(color.trim())?, position))
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+        if stops.len() < 2 {
+            return Err(invalid());
+        }
+        let last = (stops.len() - 1) as f64;
+        let stops: Vec<_> = stops
+            .into_iter()
+            .enumerate()
+            .map(|(i, (color, position))| (position.unwrap_or(i as f64 / last), color))
+            .collect();
+        let is_sorted = stops.windows(2).all(|pair| pair[0].0 <= pair[1].0);
+        if !is_sorted || stops.iter().any(|&(t, _)| !(0.0..=1.0).contains(&t)) {
+            return Err(invalid());
+        }
+        Ok(Self { stops })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gradient() -> Result<(), UnicodeArtError> {
+        let gradient = Gradient::grayscale();
+        assert_eq!(Rgba([0, 0, 0, 255]), gradient.at(-1.0));
+        assert_eq!(Rgba([128, 128, 128, 255]), gradient.at(0.5));
+        assert_eq!(Rgba([255, 255, 255, 255]), gradient.at(2.0));
+        assert_eq!(Rgba([0xfd, 0xe7, 0x25, 255]), Gradient::viridis().at(1.0));
+        assert_eq!(Rgba([0xc0, 0, 0, 255]), Gradient::fire().at(0.35));
+
+        let gradient: Gradient = "#000, #f00@0.25 ,#fff".parse()?;
+        assert_eq!(Rgba([255, 0, 0, 255]), gradient.at(0.25));
+        assert_eq!(Rgba([255, 128, 128, 255]), gradient.at(0.625));
+        for invalid in ["#000", "#000,nope", "#000@0.5,#fff@0.2", "#000,#fff@2"] {
+            assert!(invalid.parse::<Gradient>().is_err(), "{}", invalid);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,365 @@
+use image::Rgb;
+use unicode_width::UnicodeWidthChar;
+
+use super::color::ansi_256;
+
+/// Stands for the right half of a wide character like an emoji, which the
+/// cell to its left draws
+pub const WIDE_TAIL: char = '\0';
+
+/// Colors and character of a single terminal cell. `None` colors are the
+/// terminal defaults.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub ch: char,
+    pub fg: Option<Rgb<u8>>,
+    pub bg: Option<Rgb<u8>>,
+}
+
+impl Cell {
+    pub fn is_wide_tail(&self) -> bool {
+        self.ch == WIDE_TAIL
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            ch: ' ',
+            fg: None,
+            bg: None,
+        }
+    }
+}
+
+/// SGR attributes currently in effect
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Style {
+    pub fg: Option<Rgb<u8>>,
+    pub bg: Option<Rgb<u8>>,
+}
+
+impl Style {
+    /// SGR sequence switching from `self` to `target`, empty if nothing changes.
+    pub fn transition(&self, target: &Style) -> String {
+        let mut params = Vec::new();
+        if *target == Style::default() {
+            params.push("0".to_string());
+        } else {
+            if target.fg != self.fg {
+                params.push(match target.fg {
+                    Some(Rgb([r, g, b])) => format!("38;2;{};{};{}", r, g, b),
+                    None => "39".to_string(),
+                });
+            }
+            if target.bg != self.bg {
+                params.push(match target.bg {
+                    Some(Rgb([r, g, b])) => format!("48;2;{};{};{}", r, g, b),
+                    None => "49".to_string(),
+                });
+            }
+        }
+        match self == target {
+            true => String::new(),
+            false => format!("\x1B[{}m", params.join(";")),
+        }
+    }
+}
+
+/// 16 colour state, where bold and blink brighten the basic colours
+#[derive(Debug, Default, Clone, PartialEq)]
+struct BasicColors {
+    fg: Option<u8>,
+    bg: Option<u8>,
+    bold: bool,
+    blink: bool,
+}
+
+/// The cells a renderer's ANSI output paints on screen. Applying output to a
+/// grid behaves like a tiny terminal that understands cursor positioning,
+/// clearing the screen and SGR colours: truecolor, 256 and 16 colours.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CellGrid {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+    cursor: (usize, usize),
+    style: Style,
+    basic: BasicColors,
+}
+
+impl CellGrid {
+    pub fn parse(text: &str) -> Self {
+        let mut grid = Self::default();
+        grid.apply(text);
+        grid
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Cell {
+        match row < self.height && col < self.width {
+            true => self.cells[row * self.width + col],
+            false => Cell::default(),
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, cell: Cell) {
+        if row < self.height && col < self.width {
+            self.cells[row * self.width + col] = cell;
+        }
+    }
+
+    /// ANSI text painting the cells, every row ends with the default colors.
+    /// Rows are not padded with blank cells to the width of the grid.
+    pub fn to_ansi(&self) -> String {
+        let mut text = String::new();
+        for row in self.rows().take(self.height) {
+            let len = row
+                .iter()
+                .rposition(|cell| *cell != Cell::default())
+                .map_or(0, |last| last + 1);
+            let mut style = Style::default();
+            for cell in row[..len].iter().filter(|cell| !cell.is_wide_tail()) {
+                let target = Style {
+                    fg: cell.fg,
+                    bg: cell.bg,
+                };
+                text += &style.transition(&target);
+                text.push(cell.ch);
+                style = target;
+            }
+            text += &style.transition(&Style::default());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Same size and same cells, regardless of the cursor and style state.
+    #[cfg(test)]
+    pub fn same_cells(&self, other: &CellGrid) -> bool {
+        self.width == other.width && self.height == other.height && self.cells == other.cells
+    }
+
+    fn resize(&mut self, width: usize, height: usize) {
+        if width == self.width && height == self.height {
+            return;
+        }
+        let mut cells = vec![Cell::default(); width * height];
+        for (row, line) in self.rows().enumerate().take(self.height) {
+            cells[row * width..row * width + line.len()].copy_from_slice(line);
+        }
+        self.cells = cells;
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Wide characters take two columns, the second holds a `WIDE_TAIL`.
+    fn put(&mut self, ch: char) {
+        let (row, col) = self.cursor;
+        let columns = match ch.width() {
+            Some(2) => 2,
+            _ => 1,
+        };
+        self.resize(self.width.max(col + columns), self.height.max(row + 1));
+        let start = row * self.width + col;
+        let end = start + columns;
+        // like a terminal, blank what is left of wide characters cut in half
+        if col > 0 && self.cells[start].is_wide_tail() {
+            self.cells[start - 1].ch = ' ';
+        }
+        if col + columns < self.width && self.cells[end].is_wide_tail() {
+            self.cells[end].ch = ' ';
+        }
+        let cell = Cell {
+            ch,
+            fg: self.style.fg,
+            bg: self.style.bg,
+        };
+        self.cells[start] = cell;
+        if columns == 2 {
+            self.cells[start + 1] = Cell {
+                ch: WIDE_TAIL,
+                ..cell
+            };
+        }
+        self.cursor = (row, col + columns);
+    }
+
+    fn select_graphic_rendition(&mut self, params: &str) {
+        let mut params = params.split(';').map(|p| p.parse::<u32>().unwrap_or(0));
+        while let Some(param) = params.next() {
+            match param {
+                0 => {
+                    self.style = Style::default();
+                    self.basic = BasicColors::default();
+                }
+                1 => self.basic.bold = true,
+                5 => self.basic.blink = true,
+                22 => self.basic.bold = false,
+                25 => self.basic.blink = false,
+                30..=37 => self.basic.fg = Some(param as u8 - 30),
+                90..=97 => self.basic.fg = Some(param as u8 - 90 + 8),
+                40..=47 => self.basic.bg = Some(param as u8 - 40),
+                100..=107 => self.basic.bg = Some(param as u8 - 100 + 8),
+                38 | 48 => {
+                    let color = match params.next() {
+                        Some(2) => {
+                            let mut channel = || params.next().unwrap_or(0).min(255) as u8;
+                            Some(Rgb([channel(), channel(), channel()]))
+                        }
+                        Some(5) => Some(ansi_256(params.next().unwrap_or(0).min(255) as u8)),
+                        _ => None,
+                    };
+                    match param {
+                        38 => {
+                            self.basic.fg = None;
+                            self.style.fg = color;
+                        }
+                        _ => {
+                            self.basic.bg = None;
+                            self.style.bg = color;
+                        }
+                    }
+                }
+                39 => {
+                    self.basic.fg = None;
+                    self.style.fg = None;
+                }
+                49 => {
+                    self.basic.bg = None;
+                    self.style.bg = None;
+                }
+                _ => {}
+            }
+            // bold brightens and, with iCE colours, blink brightens the background
+            if let Some(index) = self.basic.fg {
+                let bright = (index < 8 && self.basic.bold) as u8 * 8;
+                self.style.fg = Some(ansi_256(index + bright));
+            }
+            if let Some(index) = self.basic.bg {
+                let bright = (index < 8 && self.basic.blink) as u8 * 8;
+                self.style.bg = Some(ansi_256(index + bright));
+            }
+        }
+    }
+
+    pub fn apply(&mut self, text: &str) {
+        let mut chars = text.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\x1B' => {
+                    if chars.next() != Some('[') {
+                        continue;
+                    }
+                    let mut params = String::new();
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7E').contains(&c) {
+                            self.control_sequence(&params, c);
+                            break;
+                        }
+                        params.push(c);
+                    }
+                }
+                '\n' => self.cursor = (self.cursor.0 + 1, 0),
+                '\r' => self.cursor.1 = 0,
+                _ => self.put(ch),
+            }
+        }
+    }
+
+    fn control_sequence(&mut self, params: &str, command: char) {
+        match command {
+            'm' => self.select_graphic_rendition(params),
+            'H' => {
+                let mut pos = params.split(';').map(|p| p.parse::<usize>().unwrap_or(1));
+                let row = pos.next().unwrap_or(1).max(1);
+                let col = pos.next().unwrap_or(1).max(1);
+                self.cursor = (row - 1, col - 1);
+            }
+            'J' if params == "2" => {
+                self.cells.clear();
+                self.width = 0;
+                self.height = 0;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let grid = CellGrid::parse("ab\n\x1B[38;2;1;2;3m\x1B[48;2;4;5;6mc\x1B[49md\x1B[0m\n");
+        assert_eq!((2, 2), (grid.width(), grid.height()));
+        assert_eq!('a', grid.get(0, 0).ch);
+        let cell = grid.get(1, 0);
+        assert_eq!(
+            ('c', Some(Rgb([1, 2, 3])), Some(Rgb([4, 5, 6]))),
+            (cell.ch, cell.fg, cell.bg)
+        );
+        let cell = grid.get(1, 1);
+        assert_eq!(
+            ('d', Some(Rgb([1, 2, 3])), None),
+            (cell.ch, cell.fg, cell.bg)
+        );
+
+        let mut grid = grid;
+        assert_eq!(
+            "ab\n\x1B[38;2;1;2;3;48;2;4;5;6mc\x1B[49md\x1B[0m\n",
+            grid.to_ansi()
+        );
+        grid.set(0, 1, Cell::default());
+        assert_eq!(' ', grid.get(0, 1).ch);
+        grid.apply("\x1B[2;2Hx\x1B[Hy");
+        assert_eq!(('y', 'x'), (grid.get(0, 0).ch, grid.get(1, 1).ch));
+        grid.apply("\x1B[2J");
+        assert_eq!((0, 0), (grid.width(), grid.height()));
+    }
+
+    #[test]
+    fn test_wide_chars() {
+        let mut grid = CellGrid::parse("🟥  \x1B[31m⬛\n");
+        assert_eq!((6, 1), (grid.width(), grid.height()));
+        assert!(grid.get(0, 1).is_wide_tail());
+        assert_eq!(
+            ('⬛', Some(Rgb([0xAA, 0, 0]))),
+            (grid.get(0, 4).ch, grid.get(0, 5).fg)
+        );
+        assert_eq!("🟥  \x1B[38;2;170;0;0m⬛\x1B[0m\n", grid.to_ansi());
+        // overwriting half of a wide character blanks the other half
+        grid.apply("\x1B[1;2Hab\x1B[1;6Hc");
+        let row: String = (0..6).map(|col| grid.get(0, col).ch).collect();
+        assert_eq!(" ab  c", row);
+    }
+
+    #[test]
+    fn test_palette_colors() {
+        let grid = CellGrid::parse("\x1B[31ma\x1B[1mb\x1B[0;5;44;38;5;208mc\x1B[22;97;49md\n");
+        let colors: Vec<_> = (0..4)
+            .map(|col| (grid.get(0, col).fg, grid.get(0, col).bg))
+            .collect();
+        assert_eq!(
+            vec![
+                (Some(Rgb([0xAA, 0, 0])), None),
+                (Some(Rgb([0xFF, 0x55, 0x55])), None),
+                (Some(Rgb([255, 135, 0])), Some(Rgb([0x55, 0x55, 0xFF]))),
+                (Some(Rgb([255, 255, 255])), None),
+            ],
+            colors
+        );
+    }
+}
@@ -1,87 +1,133 @@
-use std::io::{self, BufRead, Read, Seek, StdinLock};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom, Write};
 
-#[derive(PartialEq)]
-enum StdInStatus {
-    Init,
-    SeekCurrent,
-    SeekStart,
-    HeaderSent,
+/// Inputs up to this size are kept in memory.
+pub const DEFAULT_MEMORY_LIMIT: u64 = 64 * 1024 * 1024;
+
+enum Buffer {
+    Memory(Cursor<Vec<u8>>),
+    File(BufReader<File>),
 }
 
-pub struct Input<R: BufRead> {
-    source: R,
-    header: [u8; 16],
-    header_offset: usize,
-    status: StdInStatus,
+/// Seekable copy of a non-seekable source such as stdin, so that any decoder
+/// can jump around in it. The source is read to the end upfront; anything
+/// larger than the memory limit spills to an anonymous temporary file.
+pub struct Input {
+    buffer: Buffer,
 }
 
-impl Input<StdinLock<'static>> {
-    pub fn stdin(stdin: io::Stdin) -> Input<StdinLock<'static>> {
-        Input {
-            source: stdin.lock(),
-            header: [0; 16],
-            header_offset: 0,
-            status: StdInStatus::Init,
+impl Input {
+    pub fn new<R: Read>(mut source: R, memory_limit: u64) -> io::Result<Self> {
+        let mut head = Vec::new();
+        source
+            .by_ref()
+            .take(memory_limit.saturating_add(1))
+            .read_to_end(&mut head)?;
+        if head.len() as u64 <= memory_limit {
+            return Ok(Self {
+                buffer: Buffer::Memory(Cursor::new(head)),
+            });
         }
+        let mut file = tempfile::tempfile()?;
+        file.write_all(&head)?;
+        io::copy(&mut source, &mut file)?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(Self {
+            buffer: Buffer::File(BufReader::new(file)),
+        })
     }
 
-    // /// Get a reference to the input's source.
-    // fn source(&self) -> &dyn BufRead {
-    //     self.source.as_ref()
-    // }
+    #[cfg(test)]
+    pub fn is_in_memory(&self) -> bool {
+        matches!(self.buffer, Buffer::Memory(_))
+    }
 }
 
-impl Seek for Input<StdinLock<'static>> {
-    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
-        if pos == io::SeekFrom::Current(0) {
-            self.status = StdInStatus::SeekCurrent;
-        } else if pos == io::SeekFrom::Start(0) {
-            self.status = StdInStatus::SeekStart;
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.buffer {
+            Buffer::Memory(cursor) => cursor.read(buf),
+            Buffer::File(file) => file.read(buf),
         }
-        Ok(0)
     }
 }
 
-impl Read for Input<StdinLock<'static>> {
-    /**
-     * Stdin couldn't be seek, a workaround to resend the 16 bytes header
-     */
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        if self.status == StdInStatus::SeekCurrent {
-            // read 16 bytes header
-            let res = self.source.read(buf);
-            self.header.copy_from_slice(buf);
-            return res;
-        } else if self.status == StdInStatus::SeekStart {
-            if self.header_offset < self.header.len() {
-                let start = self.header_offset;
-                let end = self.header.len().min(start + buf.len());
-                // println!(
-                //     "read head bytes = {}, offset: {}, start = {}, end = {}",
-                //     buf.len(),
-                //     self.header_offset,
-                //     start,
-                //     end
-                // );
-                let write_len = end - start;
-                buf[..write_len].copy_from_slice(&self.header[start..end]);
-                self.header_offset += write_len;
-                if self.header_offset >= self.header.len() {
-                    self.status = StdInStatus::HeaderSent;
-                }
-                return Ok(write_len);
-            }
+impl Seek for Input {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match &mut self.buffer {
+            Buffer::Memory(cursor) => cursor.seek(pos),
+            Buffer::File(file) => file.seek(pos),
         }
-        self.source.read(buf)
     }
 }
 
-impl BufRead for Input<StdinLock<'static>> {
+impl BufRead for Input {
     fn fill_buf(&mut self) -> io::Result<&[u8]> {
-        self.source.fill_buf()
+        match &mut self.buffer {
+            Buffer::Memory(cursor) => cursor.fill_buf(),
+            Buffer::File(file) => file.fill_buf(),
+        }
     }
 
     fn consume(&mut self, amt: usize) {
-        self.source.consume(amt);
+        match &mut self.buffer {
+            Buffer::Memory(cursor) => cursor.consume(amt),
+            Buffer::File(file) => file.consume(amt),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unicode_art::animation::decode_frames;
+    use crate::unicode_art::error::UnicodeArtError;
+    use image::{DynamicImage, GenericImageView, ImageOutputFormat, Rgb, RgbImage};
+
+    // hides `Seek` like a pipe does
+    struct Pipe<R: Read>(R);
+
+    impl<R: Read> Read for Pipe<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            // short reads, as pipes deliver data in chunks
+            let len = buf.len().min(7);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn test_seek() -> io::Result<()> {
+        let data: Vec<u8> = (0..100).collect();
+        for limit in [1000, 10] {
+            let mut input = Input::new(Pipe(Cursor::new(data.clone())), limit)?;
+            assert_eq!(limit == 1000, input.is_in_memory());
+            let mut buf = [0; 4];
+            input.seek(SeekFrom::Start(50))?;
+            input.read_exact(&mut buf)?;
+            assert_eq!([50, 51, 52, 53], buf);
+            input.seek(SeekFrom::End(-2))?;
+            input.read_exact(&mut buf[..2])?;
+            assert_eq!([98, 99], buf[..2]);
+            input.seek(SeekFrom::Start(0))?;
+            let mut all = Vec::new();
+            input.read_to_end(&mut all)?;
+            assert_eq!(data, all);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_tiff() -> Result<(), UnicodeArtError> {
+        // TIFF decoders seek to the image directory at the end of the file
+        let image = RgbImage::from_fn(5, 3, |x, y| Rgb([x as u8 * 50, y as u8 * 100, 7]));
+        let mut bytes = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(image.clone()).write_to(&mut bytes, ImageOutputFormat::Tiff)?;
+        for limit in [DEFAULT_MEMORY_LIMIT, 16] {
+            let input = Input::new(Pipe(Cursor::new(bytes.get_ref().clone())), limit)?;
+            let (frames, _) = decode_frames(input)?;
+            assert_eq!((5, 3), frames[0].content.dimensions());
+            assert_eq!(image, frames[0].content.to_rgb8());
+        }
+        Ok(())
     }
 }
@@ -1,7 +1,11 @@
-use image::{DynamicImage, GenericImageView, Pixel};
+use image::{DynamicImage, GenericImageView, Pixel, Rgb};
 
 pub trait Mean {
     fn mean(&self, sx: u32, ex: u32, sy: u32, ey: u32) -> u8;
+    fn mean_alpha(&self, sx: u32, ex: u32, sy: u32, ey: u32) -> u8;
+    /// Mean of every channel, weighted by alpha so that transparent pixels
+    /// don't darken the edges of a shape.
+    fn mean_color(&self, sx: u32, ex: u32, sy: u32, ey: u32) -> Rgb<u8>;
 }
 
 impl Mean for DynamicImage {
@@ -14,12 +18,75 @@ impl Mean for DynamicImage {
             println!("sx = {}, sy = {}, ex = {} , ey = {}", sx, sy, ex, ey);
         }
         debug_assert_ne!(len, 0);
-        let sum = sub_image.pixels().fold(0u32, |mut sum, &pixel| {
+        let sum = sub_image.pixels().fold(0u64, |mut sum, &pixel| {
             let image::Rgb(data): image::Rgb<u8> = pixel.to_rgb();
             let [r, g, b] = data;
-            sum += r as u32 + g as u32 + b as u32;
+            sum += r as u64 + g as u64 + b as u64;
             sum
         });
-        (sum / 3 / len as u32) as u8
+        (sum / 3 / len as u64) as u8
+    }
+
+    fn mean_alpha(&self, sx: u32, ex: u32, sy: u32, ey: u32) -> u8 {
+        let sub_image = self.view(sx, sy, 1.max(ex - sx), 1.max(ey - sy));
+        let sub_image = sub_image.to_image();
+
+        let len = sub_image.pixels().len();
+        debug_assert_ne!(len, 0);
+        let sum = sub_image
+            .pixels()
+            .fold(0u64, |sum, pixel| sum + pixel[3] as u64);
+        (sum / len as u64) as u8
+    }
+
+    fn mean_color(&self, sx: u32, ex: u32, sy: u32, ey: u32) -> Rgb<u8> {
+        let sub_image = self.view(sx, sy, 1.max(ex - sx), 1.max(ey - sy));
+        let sub_image = sub_image.to_image();
+
+        debug_assert_ne!(sub_image.pixels().len(), 0);
+        // whole source spans of large photos overflow u32
+        let (sum, weight) =
+            sub_image
+                .pixels()
+                .fold(([0u64; 3], 0u64), |(mut sum, weight), pixel| {
+                    let alpha = pixel[3] as u64;
+                    for c in 0..3 {
+                        sum[c] += pixel[c] as u64 * alpha;
+                    }
+                    (sum, weight + alpha)
+                });
+        let weight = weight.max(1);
+        Rgb([
+            (sum[0] / weight) as u8,
+            (sum[1] / weight) as u8,
+            (sum[2] / weight) as u8,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_mean_color() {
+        let mut image = RgbaImage::from_pixel(4, 2, Rgba([200, 100, 0, 255]));
+        image.put_pixel(1, 0, Rgba([0, 100, 200, 255]));
+        // transparent pixels don't count, whatever their color
+        image.put_pixel(2, 0, Rgba([255, 255, 255, 0]));
+        let image = DynamicImage::ImageRgba8(image);
+        assert_eq!(Rgb([100, 100, 100]), image.mean_color(0, 2, 0, 1));
+        assert_eq!(Rgb([200, 100, 0]), image.mean_color(2, 4, 0, 2));
+    }
+
+    #[test]
+    fn test_mean_large_cell() {
+        // a cell of a 12 MP photo at 20 columns
+        let image =
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(400, 800, Rgba([250, 128, 3, 255])));
+        assert_eq!(Rgb([250, 128, 3]), image.mean_color(0, 400, 0, 800));
+        assert_eq!(127, image.mean(0, 400, 0, 800));
+        assert_eq!(255, image.mean_alpha(0, 400, 0, 800));
     }
 }
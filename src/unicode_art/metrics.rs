@@ -0,0 +1,183 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, GrayImage, RgbImage, Rgba};
+
+use super::alpha::Composite;
+use super::error::UnicodeArtError;
+use super::grid::CellGrid;
+use super::reverse::{grid_to_image, ReverseMode};
+use super::UnicodeArtOption;
+
+/// Pixels per cell that scores are computed at, the dots of a braille cell
+/// are the finest detail of any mode
+const SAMPLES_X: u32 = 2;
+const SAMPLES_Y: u32 = 4;
+const SSIM_WINDOW: u32 = 8;
+const SSIM_STEP: usize = 4;
+const SSIM_C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const SSIM_C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+/// Quality of one render mode against the source image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Score {
+    pub name: String,
+    /// Peak signal to noise ratio in dB, infinite for identical images
+    pub psnr: f64,
+    /// Mean structural similarity of the luma, 1 for identical images
+    pub ssim: f64,
+}
+
+/// Peak signal to noise ratio over all channels of two images of the same size.
+pub fn psnr(a: &RgbImage, b: &RgbImage) -> f64 {
+    let (sum, count) = a
+        .as_raw()
+        .iter()
+        .zip(b.as_raw())
+        .fold((0.0, 0usize), |(sum, count), (&x, &y)| {
+            (sum + (x as f64 - y as f64).powi(2), count + 1)
+        });
+    let mse = sum / count.max(1) as f64;
+    if mse == 0.0 {
+        return f64::INFINITY;
+    }
+    10.0 * (255.0 * 255.0 / mse).log10()
+}
+
+fn window_ssim(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len() as f64;
+    let mean_a = a.iter().sum::<f64>() / n;
+    let mean_b = b.iter().sum::<f64>() / n;
+    let (mut var_a, mut var_b, mut covar) = (0.0, 0.0, 0.0);
+    for (&x, &y) in a.iter().zip(b) {
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+        covar += (x - mean_a) * (y - mean_b);
+    }
+    let (var_a, var_b, covar) = (var_a / n, var_b / n, covar / n);
+    ((2.0 * mean_a * mean_b + SSIM_C1) * (2.0 * covar + SSIM_C2))
+        / ((mean_a.powi(2) + mean_b.powi(2) + SSIM_C1) * (var_a + var_b + SSIM_C2))
+}
+
+/// Mean structural similarity of two grayscale images of the same size, over
+/// 8x8 windows that overlap by half.
+pub fn ssim(a: &GrayImage, b: &GrayImage) -> f64 {
+    let (width, height) = a.dimensions();
+    let (window_w, window_h) = (SSIM_WINDOW.min(width), SSIM_WINDOW.min(height));
+    let mut total = 0.0;
+    let mut count = 0;
+    for y in (0..=height - window_h).step_by(SSIM_STEP) {
+        for x in (0..=width - window_w).step_by(SSIM_STEP) {
+            let window = |image: &GrayImage| -> Vec<f64> {
+                image
+                    .view(x, y, window_w, window_h)
+                    .pixels()
+                    .map(|(_, _, pixel)| pixel[0] as f64)
+                    .collect()
+            };
+            total += window_ssim(&window(a), &window(b));
+            count += 1;
+        }
+    }
+    total / count.max(1) as f64
+}
+
+/// Render `image`, turn the output back into pixels and score it against
+/// the source scaled down to the same size. Every mode is compared at 2x4
+/// pixels per cell, glyphs are averaged down like an eye at a distance, so
+/// scores of modes with the same number of cells can be ranked.
+pub fn score(
+    name: &str,
+    option: &dyn UnicodeArtOption,
+    image: &DynamicImage,
+    is_invert: bool,
+) -> Result<Score, UnicodeArtError> {
+    let mut buf = Vec::new();
+    option.new_unicode_art(image)?.write_all(&mut buf)?;
+    let grid = CellGrid::parse(&String::from_utf8_lossy(&buf));
+    let width = grid.width().max(1) as u32 * SAMPLES_X;
+    let height = grid.height().max(1) as u32 * SAMPLES_Y;
+    // transparent pixels show the paper
+    let paper = match is_invert {
+        true => Rgba([0, 0, 0, 255]),
+        false => Rgba([255, 255, 255, 255]),
+    };
+    let rendered = grid_to_image(&grid, ReverseMode::Auto, is_invert)?.composite(paper);
+    let filter = match rendered.width() < width {
+        true => FilterType::Nearest,
+        false => FilterType::Triangle,
+    };
+    let rendered = rendered.resize_exact(width, height, filter);
+    let source = image
+        .resize_exact(width, height, FilterType::Triangle)
+        .composite(paper);
+    Ok(Score {
+        name: name.to_string(),
+        psnr: psnr(&rendered.to_rgb8(), &source.to_rgb8()),
+        ssim: ssim(&rendered.to_luma8(), &source.to_luma8()),
+    })
+}
+
+/// Score every named render mode on the same image.
+pub fn compare(
+    image: &DynamicImage,
+    candidates: &[(&str, &dyn UnicodeArtOption)],
+    is_invert: bool,
+) -> Result<Vec<Score>, UnicodeArtError> {
+    candidates
+        .iter()
+        .map(|(name, option)| score(name, *option, image, is_invert))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use image::io::Reader;
+    use image::Luma;
+
+    use super::super::aspect_ratio::{SimpleAspectRatio, TermFit};
+    use super::super::block::BlockUnicodeArtOption;
+    use super::super::braille::BrailleAsciiArtOption;
+    use super::super::classic::ClassicAsciiArtOption;
+    use super::*;
+
+    #[test]
+    fn test_metrics() {
+        let a = GrayImage::from_fn(16, 16, |x, y| Luma([(x * 16 + y) as u8]));
+        let mut b = a.clone();
+        assert_eq!(1.0, ssim(&a, &b));
+        b.put_pixel(3, 3, Luma([255]));
+        assert!(ssim(&a, &b) < 1.0);
+
+        let a = DynamicImage::ImageLuma8(a).to_rgb8();
+        assert_eq!(f64::INFINITY, psnr(&a, &a));
+        let b = RgbImage::from_fn(16, 16, |x, y| {
+            let v = a.get_pixel(x, y)[0] as i32 + if (x + y) % 2 == 0 { 5 } else { -5 };
+            image::Rgb([v.clamp(0, 255) as u8; 3])
+        });
+        // an error of 5 everywhere is about 34 dB
+        assert!((psnr(&a, &b) - 34.15).abs() < 0.2);
+    }
+
+    #[test]
+    fn test_quality_regression() -> Result<(), UnicodeArtError> {
+        let image = Reader::open("tests/support/test_gundam.png")?.decode()?;
+        let aspect_ratio = || SimpleAspectRatio::new(Some(40), Some(20), TermFit::None, false);
+        let block = BlockUnicodeArtOption::new(40, true).with_aspect_ratio(aspect_ratio());
+        let braille =
+            BrailleAsciiArtOption::new(40, 127, false, false).with_aspect_ratio(aspect_ratio());
+        let classic =
+            ClassicAsciiArtOption::new_standard(40, false, false).with_aspect_ratio(aspect_ratio());
+        let scores = compare(
+            &image,
+            &[
+                ("block", &block),
+                ("braille", &braille),
+                ("standard", &classic),
+            ],
+            false,
+        )?;
+        // colour blocks reproduce the thumbnail, the others only shapes
+        assert!(scores[0].psnr > scores[1].psnr && scores[0].psnr > scores[2].psnr);
+        assert!(scores[0].ssim > 0.0 && scores[1].ssim > 0.0 && scores[2].ssim > 0.0);
+        Ok(())
+    }
+}
@@ -1,28 +1,60 @@
-mod aspect_ratio;
+pub mod alpha;
+pub mod animation;
+pub mod ansiart;
+pub mod asciicast;
+pub mod aspect_ratio;
 pub mod block;
 pub mod braille;
 pub mod classic;
-mod color;
+pub mod color;
+pub mod colorize;
+pub mod delta;
+pub mod emoji;
 pub mod error;
+pub mod export;
+pub mod field;
+pub mod figlet;
+pub mod fractal;
+pub mod gradient;
+pub mod grid;
 pub mod input;
-pub mod mandel;
 mod mean;
+pub mod metrics;
+pub mod preprocess;
+pub mod procedural;
+pub mod raster;
+pub mod reverse;
+pub mod stream;
 pub mod subpixel;
+pub mod term;
+pub mod transform;
+pub mod ttf;
 
 use image::DynamicImage;
 
 use crate::unicode_art::error::UnicodeArtError;
-use std::io::{BufRead, Seek, Write};
-
-pub trait SeekBufRead: Seek + BufRead + Sync {}
+use std::io::Write;
 
 pub trait UnicodeArt {
     fn write_all(&self, writer: &mut dyn Write) -> Result<(), UnicodeArtError>;
 }
 
-pub trait UnicodeArtOption {
+/// Renderer settings, shared between the threads of a batch run.
+pub trait UnicodeArtOption: Sync {
     fn new_unicode_art<'a>(
         &'a self,
         image: &'a DynamicImage,
     ) -> Result<Box<dyn UnicodeArt + 'a>, UnicodeArtError>;
 }
+
+/// Something that can be drawn as a bitmap, so that image renderers can
+/// turn it into art.
+pub trait ImageSource {
+    /// A bitmap `width` pixels wide, as tall as the source's own proportions.
+    fn to_image(&self, width: u32) -> Result<DynamicImage, UnicodeArtError>;
+}
+
+/// Settings of art generated without an input image, like patterns.
+pub trait PatternOption: ImageSource + Sync {
+    fn new_pattern_art(&self) -> Result<Box<dyn UnicodeArt + '_>, UnicodeArtError>;
+}
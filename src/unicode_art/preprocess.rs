@@ -0,0 +1,188 @@
+use image::{DynamicImage, RgbaImage};
+
+/// Tonal adjustments applied to an image before it reaches a renderer.
+///
+/// Whatever combination is enabled, the operations always run in the same
+/// order: auto-levels, equalize, brightness, contrast, gamma, blur, sharpen.
+#[derive(Debug, Default, Clone)]
+pub struct Preprocess {
+    pub(crate) auto_levels: bool,
+    pub(crate) equalize: bool,
+    pub(crate) brightness: Option<i32>,
+    pub(crate) contrast: Option<f32>,
+    pub(crate) gamma: Option<f32>,
+    pub(crate) blur: Option<f32>,
+    pub(crate) sharpen: Option<f32>,
+}
+
+type Lut = [[u8; 256]; 3];
+
+impl Preprocess {
+    pub fn is_noop(&self) -> bool {
+        !self.auto_levels
+            && !self.equalize
+            && self.brightness.is_none()
+            && self.contrast.is_none()
+            && self.gamma.is_none()
+            && self.blur.is_none()
+            && self.sharpen.is_none()
+    }
+
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        if self.is_noop() {
+            return image;
+        }
+        let mut img = image;
+        if self.auto_levels {
+            img = map_channels(&img, auto_levels_lut);
+        }
+        if self.equalize {
+            img = map_channels(&img, equalize_lut);
+        }
+        if let Some(brightness) = self.brightness {
+            img = img.brighten(brightness);
+        }
+        if let Some(contrast) = self.contrast {
+            img = img.adjust_contrast(contrast);
+        }
+        if let Some(gamma) = self.gamma {
+            img = map_channels(&img, |_| gamma_lut(gamma));
+        }
+        if let Some(sigma) = self.blur {
+            img = img.blur(sigma);
+        }
+        if let Some(sigma) = self.sharpen {
+            img = img.unsharpen(sigma, 1);
+        }
+        img
+    }
+}
+
+/// Remap the RGB channels through per-channel lookup tables built from the
+/// image itself. Alpha is left untouched.
+fn map_channels<F>(image: &DynamicImage, build_lut: F) -> DynamicImage
+where
+    F: Fn(&RgbaImage) -> Lut,
+{
+    let mut img = image.to_rgba8();
+    let lut = build_lut(&img);
+    for pixel in img.pixels_mut() {
+        for c in 0..3 {
+            pixel[c] = lut[c][pixel[c] as usize];
+        }
+    }
+    DynamicImage::ImageRgba8(img)
+}
+
+fn histograms(img: &RgbaImage) -> [[u32; 256]; 3] {
+    let mut hist = [[0u32; 256]; 3];
+    for pixel in img.pixels() {
+        for c in 0..3 {
+            hist[c][pixel[c] as usize] += 1;
+        }
+    }
+    hist
+}
+
+/// Stretch each channel so that its darkest value maps to 0 and its
+/// brightest to 255.
+fn auto_levels_lut(img: &RgbaImage) -> Lut {
+    let hist = histograms(img);
+    let mut lut = [[0u8; 256]; 3];
+    for c in 0..3 {
+        let min = hist[c].iter().position(|&n| n > 0).unwrap_or(0);
+        let max = hist[c].iter().rposition(|&n| n > 0).unwrap_or(255);
+        for (v, out) in lut[c].iter_mut().enumerate() {
+            *out = if max <= min {
+                v as u8
+            } else {
+                ((v.clamp(min, max) - min) * 255 / (max - min)) as u8
+            };
+        }
+    }
+    lut
+}
+
+/// Flatten each channel's histogram through its cumulative distribution.
+fn equalize_lut(img: &RgbaImage) -> Lut {
+    let hist = histograms(img);
+    let total = img.pixels().len() as u64;
+    let mut lut = [[0u8; 256]; 3];
+    for c in 0..3 {
+        let cdf_min = hist[c].iter().find(|&&n| n > 0).copied().unwrap_or(0) as u64;
+        let mut cdf = 0u64;
+        for (v, out) in lut[c].iter_mut().enumerate() {
+            cdf += hist[c][v] as u64;
+            *out = if total <= cdf_min {
+                v as u8
+            } else {
+                ((cdf.saturating_sub(cdf_min)) * 255 / (total - cdf_min)) as u8
+            };
+        }
+    }
+    lut
+}
+
+fn gamma_lut(gamma: f32) -> Lut {
+    let mut channel = [0u8; 256];
+    for (v, out) in channel.iter_mut().enumerate() {
+        *out = (255.0 * (v as f32 / 255.0).powf(1.0 / gamma)).round() as u8;
+    }
+    [channel; 3]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, Rgba};
+
+    fn gradient() -> DynamicImage {
+        let mut img = RgbaImage::new(4, 1);
+        for (x, v) in [100u8, 110, 120, 130].iter().enumerate() {
+            img.put_pixel(x as u32, 0, Rgba([*v, *v, *v, 200]));
+        }
+        DynamicImage::ImageRgba8(img)
+    }
+
+    #[test]
+    fn test_noop() {
+        let image = gradient();
+        let actual = Preprocess::default().apply(image.clone());
+        assert_eq!(image.to_rgba8().into_raw(), actual.to_rgba8().into_raw());
+    }
+
+    #[test]
+    fn test_auto_levels() {
+        let opt = Preprocess {
+            auto_levels: true,
+            ..Default::default()
+        };
+        let img = opt.apply(gradient());
+        let values: Vec<u8> = img.pixels().map(|(_, _, p)| p[0]).collect();
+        assert_eq!(vec![0, 85, 170, 255], values);
+        // alpha is preserved
+        assert_eq!(200, img.get_pixel(0, 0)[3]);
+    }
+
+    #[test]
+    fn test_equalize() {
+        let opt = Preprocess {
+            equalize: true,
+            ..Default::default()
+        };
+        let img = opt.apply(gradient());
+        let values: Vec<u8> = img.pixels().map(|(_, _, p)| p[1]).collect();
+        assert_eq!(vec![0, 85, 170, 255], values);
+    }
+
+    #[test]
+    fn test_gamma() {
+        let opt = Preprocess {
+            gamma: Some(2.0),
+            ..Default::default()
+        };
+        let img = opt.apply(gradient());
+        let values: Vec<u8> = img.pixels().map(|(_, _, p)| p[2]).collect();
+        assert_eq!(vec![160, 167, 175, 182], values);
+    }
+}
@@ -0,0 +1,588 @@
+use std::f64::consts::PI;
+use std::io::Write;
+
+use image::DynamicImage;
+
+use super::animation::AnimatedOption;
+use super::aspect_ratio::{AspectRatio, SimpleAspectRatio, TermFit};
+use super::field::FieldStyle;
+use super::fractal::CHARSET_DENSITY;
+use super::gradient::Gradient;
+use super::{error::UnicodeArtError, ImageSource, PatternOption, UnicodeArt};
+
+pub const DEFAULT_SEED: u64 = 0;
+/// Noise features, or plasma waves, across the width
+pub const DEFAULT_SCALE: f64 = 8.0;
+pub const DEFAULT_GENERATIONS: u32 = 16;
+/// The rule that looks random from a single cell
+pub const DEFAULT_RULE: u8 = 30;
+/// The patterns are laid out on a plane of 2:1 like a terminal splash screen
+/// of 80×20 cells
+const PLANE_WIDTH: f64 = 2.0;
+const PLANE_HEIGHT: f64 = 1.0;
+const NOISE_OCTAVES: u32 = 4;
+/// Noise rarely comes near -1 or 1, stretched so the whole charset is used
+const NOISE_CONTRAST: f64 = 2.0;
+/// Spreads the corner sums of simplex noise as far as Perlin noise, so that
+/// both take the same contrast
+const SIMPLEX_SCALE: f64 = 40.0;
+/// Noise and plasma scroll one unit of the scale a second
+const SCROLL_PER_SECOND: f64 = 1.0;
+/// Radians a second each plasma wave turns
+const PLASMA_SPEEDS: [f64; 4] = [1.0, 1.3, 0.7, 1.9];
+const GENERATIONS_PER_SECOND: f64 = 10.0;
+const AUTOMATON_ROWS_PER_SECOND: f64 = 10.0;
+/// Share of live cells in the random soup Life starts from
+const LIFE_DENSITY: f64 = 0.4;
+
+/// SplitMix64, small and good enough to make patterns reproducible from a
+/// seed.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in 0..1.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in 0..n, n must not be 0.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Procedural {
+    /// Perlin gradient noise summed over a few octaves
+    Noise,
+    /// Simplex noise summed over a few octaves, without the grid-aligned
+    /// streaks of Perlin noise
+    Simplex,
+    /// The demoscene plasma, a sum of sine waves
+    Plasma,
+    /// Conway's Game of Life on a torus, from a random soup
+    Life,
+    /// An elementary cellular automaton by rule number, one generation per
+    /// row from a single live cell
+    Automaton(u8),
+    /// A perfect maze carved by a randomized depth-first search
+    Maze,
+}
+
+/// Classic Perlin and simplex noise with a permutation shuffled by the seed.
+struct GradientNoise {
+    permutation: [u8; 512],
+}
+
+impl GradientNoise {
+    fn new(rng: &mut Rng) -> Self {
+        let mut table: [u8; 256] = [0; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            *entry = i as u8;
+        }
+        for i in (1..table.len()).rev() {
+            table.swap(i, rng.below(i + 1));
+        }
+        let mut permutation = [0; 512];
+        for (i, entry) in permutation.iter_mut().enumerate() {
+            *entry = table[i % 256];
+        }
+        Self { permutation }
+    }
+
+    fn gradient(hash: u8, x: f64, y: f64) -> f64 {
+        match hash & 7 {
+            0 => x + y,
+            1 => x - y,
+            2 => -x + y,
+            3 => -x - y,
+            4 => x,
+            5 => -x,
+            6 => y,
+            _ => -y,
+        }
+    }
+
+    /// About -1..1, 0 at every lattice point.
+    fn perlin(&self, x: f64, y: f64) -> f64 {
+        let fade = |t: f64| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let lerp = |t: f64, a: f64, b: f64| a + t * (b - a);
+        let (xi, yi) = (x.floor() as i64 & 255, y.floor() as i64 & 255);
+        let (xf, yf) = (x - x.floor(), y - y.floor());
+        let p = &self.permutation;
+        let hash = |dx: i64, dy: i64| p[(p[(xi + dx) as usize] as i64 + yi + dy) as usize];
+        let (u, v) = (fade(xf), fade(yf));
+        let lower = lerp(
+            u,
+            Self::gradient(hash(0, 0), xf, yf),
+            Self::gradient(hash(1, 0), xf - 1.0, yf),
+        );
+        let upper = lerp(
+            u,
+            Self::gradient(hash(0, 1), xf, yf - 1.0),
+            Self::gradient(hash(1, 1), xf - 1.0, yf - 1.0),
+        );
+        lerp(v, lower, upper)
+    }
+
+    /// About -0.6..0.6, summed from the three corners of the triangle of a
+    /// skewed lattice that `(x, y)` falls in.
+    fn simplex(&self, x: f64, y: f64) -> f64 {
+        let skew = (3f64.sqrt() - 1.0) / 2.0;
+        let unskew = (3.0 - 3f64.sqrt()) / 6.0;
+        let s = (x + y) * skew;
+        let (i, j) = ((x + s).floor(), (y + s).floor());
+        let t = (i + j) * unskew;
+        let (x0, y0) = (x - (i - t), y - (j - t));
+        // the lower or upper triangle of the cell
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+        let p = &self.permutation;
+        let (ii, jj) = (i as i64 & 255, j as i64 & 255);
+        let hash = |di: i64, dj: i64| p[(p[(ii + di) as usize] as i64 + jj + dj) as usize];
+        let corner = |hash: u8, x: f64, y: f64| {
+            let falloff = 0.5 - x * x - y * y;
+            match falloff > 0.0 {
+                true => falloff.powi(4) * Self::gradient(hash, x, y),
+                false => 0.0,
+            }
+        };
+        let sum = corner(hash(0, 0), x0, y0)
+            + corner(
+                hash(i1, j1),
+                x0 - i1 as f64 + unskew,
+                y0 - j1 as f64 + unskew,
+            )
+            + corner(hash(1, 1), x0 - 1.0 + 2.0 * unskew, y0 - 1.0 + 2.0 * unskew);
+        SIMPLEX_SCALE * sum
+    }
+
+    /// Octaves of halving amplitude and doubling frequency, mapped to 0..1.
+    fn fractal(&self, x: f64, y: f64, noise: fn(&Self, f64, f64) -> f64) -> f64 {
+        let (mut total, mut amplitude, mut frequency, mut max) = (0.0, 1.0, 1.0, 0.0);
+        for _ in 0..NOISE_OCTAVES {
+            total += noise(self, x * frequency, y * frequency) * amplitude;
+            max += amplitude;
+            amplitude /= 2.0;
+            frequency *= 2.0;
+        }
+        ((total / max * NOISE_CONTRAST + 1.0) / 2.0).clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Clone)]
+pub struct ProceduralAsciiArtOption {
+    procedural: Procedural,
+    seed: u64,
+    scale: f64,
+    generations: u32,
+    charset: Vec<char>,
+    gradient: Option<Gradient>,
+    is_half_blocks: bool,
+    aspect_ratio: SimpleAspectRatio,
+    time: f64,
+}
+
+pub struct ProceduralAsciiArt<'a> {
+    options: &'a ProceduralAsciiArtOption,
+}
+
+impl ProceduralAsciiArtOption {
+    pub fn new(procedural: Procedural, num_cols: u32) -> Self {
+        Self {
+            procedural,
+            seed: DEFAULT_SEED,
+            scale: DEFAULT_SCALE,
+            generations: DEFAULT_GENERATIONS,
+            charset: CHARSET_DENSITY.chars().collect(),
+            gradient: None,
+            is_half_blocks: false,
+            aspect_ratio: SimpleAspectRatio::new_auto_height(num_cols, TermFit::None, false),
+            time: 0.0,
+        }
+    }
+
+    pub fn with_aspect_ratio(mut self, aspect_ratio: SimpleAspectRatio) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    /// The same seed always draws the same pattern.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Noise features, or plasma waves, across the width.
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Steps Life runs from the random soup.
+    pub fn with_generations(mut self, generations: u32) -> Self {
+        self.generations = generations;
+        self
+    }
+
+    /// Characters from low to high values, dead cells and passages get the
+    /// first, live cells and walls the last.
+    pub fn with_charset(mut self, charset: &str) -> Self {
+        self.charset = charset.chars().collect();
+        self
+    }
+
+    /// Truecolor output, values are mapped onto the gradient.
+    pub fn with_gradient(mut self, gradient: Gradient) -> Self {
+        self.gradient = Some(gradient);
+        self
+    }
+
+    /// Draw upper half blocks in two colors instead of characters, which
+    /// doubles the vertical resolution. Grayscale without a gradient.
+    pub fn with_half_blocks(mut self, is_half_blocks: bool) -> Self {
+        self.is_half_blocks = is_half_blocks;
+        self
+    }
+
+    /// Draw the pattern `time` seconds in: noise and plasma scroll, Life
+    /// runs more generations and the automaton scrolls up. The maze stays.
+    pub fn with_time(mut self, time: f64) -> Self {
+        self.time = time;
+        self
+    }
+}
+
+impl AnimatedOption for ProceduralAsciiArtOption {
+    fn at_time(&self, t: f64) -> Box<dyn PatternOption> {
+        Box::new(self.clone().with_time(self.time + t))
+    }
+}
+
+impl ImageSource for ProceduralAsciiArtOption {
+    fn to_image(&self, width: u32) -> Result<DynamicImage, UnicodeArtError> {
+        let height = ((width as f64 * PLANE_HEIGHT / PLANE_WIDTH).round() as u32).max(1);
+        let values = self.values(width as usize, height as usize);
+        let image = self
+            .style()
+            .to_image(width, height, |x, y| Some(values[(y * width + x) as usize]));
+        Ok(DynamicImage::ImageRgb8(image))
+    }
+}
+
+impl PatternOption for ProceduralAsciiArtOption {
+    fn new_pattern_art(&self) -> Result<Box<dyn UnicodeArt + '_>, UnicodeArtError> {
+        if self.charset.len() < 2 {
+            return Err(UnicodeArtError::InvalidArgumentError(
+                "charset needs at least 2 characters".to_string(),
+            ));
+        }
+        Ok(Box::new(ProceduralAsciiArt { options: self }))
+    }
+}
+
+/// The next generation of a `width` × `height` torus, row by row.
+fn next_generation(cells: &[bool], width: usize, height: usize) -> Vec<bool> {
+    (0..width * height)
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            // wrapping offsets of -1, 0 and 1
+            let offsets = [width - 1, 0, 1]
+                .into_iter()
+                .flat_map(|dx| [height - 1, 0, 1].into_iter().map(move |dy| (dx, dy)));
+            let neighbours = offsets
+                .filter(|&offset| offset != (0, 0))
+                .filter(|&(dx, dy)| cells[(y + dy) % height * width + (x + dx) % width])
+                .count();
+            matches!((cells[i], neighbours), (true, 2) | (_, 3))
+        })
+        .collect()
+}
+
+fn life(rng: &mut Rng, width: usize, height: usize, generations: u32) -> Vec<bool> {
+    let mut cells: Vec<bool> = (0..width * height)
+        .map(|_| rng.next_f64() < LIFE_DENSITY)
+        .collect();
+    for _ in 0..generations {
+        cells = next_generation(&cells, width, height);
+    }
+    cells
+}
+
+/// Generations `first..first + height`, row by row.
+fn automaton(rule: u8, width: usize, height: usize, first: usize) -> Vec<bool> {
+    let height = height + first;
+    let mut cells = vec![false; width * height];
+    if width == 0 {
+        return cells;
+    }
+    cells[width / 2] = true;
+    for y in 1..height {
+        for x in 0..width {
+            let at = |dx: usize| cells[(y - 1) * width + (x + dx) % width] as u8;
+            let pattern = at(width - 1) << 2 | at(0) << 1 | at(1);
+            cells[y * width + x] = rule & (1 << pattern) != 0;
+        }
+    }
+    cells.split_off(first * width)
+}
+
+/// Walls of a maze whose passages run through the odd coordinates.
+fn maze(rng: &mut Rng, width: usize, height: usize) -> Vec<bool> {
+    let mut walls = vec![true; width * height];
+    let (maze_width, maze_height) = (width.saturating_sub(1) / 2, height.saturating_sub(1) / 2);
+    if maze_width == 0 || maze_height == 0 {
+        return walls;
+    }
+    let mut visited = vec![false; maze_width * maze_height];
+    let mut stack = vec![(0, 0)];
+    visited[0] = true;
+    walls[width + 1] = false;
+    while let Some(&(x, y)) = stack.last() {
+        let neighbours: Vec<(usize, usize)> = [(0, -1), (1, 0), (0, 1), (-1, 0)]
+            .iter()
+            .map(|&(dx, dy)| (x as i64 + dx, y as i64 + dy))
+            .filter(|&(nx, ny)| {
+                (0..maze_width as i64).contains(&nx) && (0..maze_height as i64).contains(&ny)
+            })
+            .map(|(nx, ny)| (nx as usize, ny as usize))
+            .filter(|&(nx, ny)| !visited[ny * maze_width + nx])
+            .collect();
+        if neighbours.is_empty() {
+            stack.pop();
+            continue;
+        }
+        let (nx, ny) = neighbours[rng.below(neighbours.len())];
+        visited[ny * maze_width + nx] = true;
+        // knock down the wall between the two cells, then open the next one
+        walls[(y + ny + 1) * width + x + nx + 1] = false;
+        walls[(ny * 2 + 1) * width + nx * 2 + 1] = false;
+        stack.push((nx, ny));
+    }
+    walls
+}
+
+impl ProceduralAsciiArtOption {
+    fn style(&self) -> FieldStyle<'_> {
+        FieldStyle {
+            charset: &self.charset,
+            gradient: self.gradient.as_ref(),
+            is_half_blocks: self.is_half_blocks,
+        }
+    }
+
+    /// Values from 0 to 1 of every sample, row by row.
+    fn values(&self, width: usize, height: usize) -> Vec<f64> {
+        let options = self;
+        let mut rng = Rng::new(options.seed);
+        // the middle of every sample on the plane, `scale` units across
+        let unit = options.scale / PLANE_WIDTH;
+        let point = |i: usize| {
+            let x = ((i % width) as f64 + 0.5) / width as f64 * PLANE_WIDTH * unit
+                + options.time * SCROLL_PER_SECOND;
+            let y = ((i / width) as f64 + 0.5) / height as f64 * PLANE_HEIGHT * unit;
+            (x, y)
+        };
+        let from_cells = |cells: Vec<bool>| cells.into_iter().map(|alive| alive as u8 as f64);
+        match options.procedural {
+            Procedural::Noise | Procedural::Simplex => {
+                let noise = GradientNoise::new(&mut rng);
+                let kind = match options.procedural {
+                    Procedural::Noise => GradientNoise::perlin,
+                    _ => GradientNoise::simplex,
+                };
+                // offset by the seed, the lattice is 0 at whole coordinates
+                let (dx, dy) = (rng.next_f64() * 256.0, rng.next_f64() * 256.0);
+                (0..width * height)
+                    .map(|i| {
+                        let (x, y) = point(i);
+                        noise.fractal(x + dx, y + dy, kind)
+                    })
+                    .collect()
+            }
+            Procedural::Plasma => {
+                let phases: Vec<f64> = (0..4).map(|_| rng.next_f64() * 2.0 * PI).collect();
+                let (cx, cy) = (
+                    rng.next_f64() * PLANE_WIDTH * unit,
+                    rng.next_f64() * PLANE_HEIGHT * unit,
+                );
+                (0..width * height)
+                    .map(|i| {
+                        let (x, y) = point(i);
+                        let radius = ((x - cx).powi(2) + (y - cy).powi(2)).sqrt();
+                        let phase = |wave: usize| phases[wave] + options.time * PLASMA_SPEEDS[wave];
+                        let sum = (x * PI + phase(0)).sin()
+                            + (y * PI + phase(1)).sin()
+                            + ((x + y) * PI / 2.0 + phase(2)).sin()
+                            + (radius * PI + phase(3)).sin();
+                        (sum + 4.0) / 8.0
+                    })
+                    .collect()
+            }
+            Procedural::Life => {
+                let generations =
+                    options.generations + (options.time * GENERATIONS_PER_SECOND) as u32;
+                from_cells(life(&mut rng, width, height, generations)).collect()
+            }
+            Procedural::Automaton(rule) => {
+                let first = (options.time * AUTOMATON_ROWS_PER_SECOND) as usize;
+                from_cells(automaton(rule, width, height, first)).collect()
+            }
+            Procedural::Maze => from_cells(maze(&mut rng, width, height)).collect(),
+        }
+    }
+}
+
+impl<'a> UnicodeArt for ProceduralAsciiArt<'a> {
+    fn write_all(&self, writer: &mut dyn Write) -> Result<(), UnicodeArtError> {
+        let options = self.options;
+        let (num_cols, num_rows) = options.aspect_ratio.calculate(
+            (PLANE_WIDTH * 1000.0) as u32,
+            (PLANE_HEIGHT * 1000.0) as u32,
+        );
+        let style = options.style();
+        let (width, height) = (
+            num_cols as usize,
+            (num_rows * style.samples_per_row()) as usize,
+        );
+        let values = options.values(width, height);
+        style.write_field(writer, num_cols, num_rows, |col, sample_row| {
+            Some(values[sample_row as usize * width + col as usize])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn render(option: &ProceduralAsciiArtOption) -> Result<String, UnicodeArtError> {
+        let mut buf = Vec::new();
+        option.new_pattern_art()?.write_all(&mut buf)?;
+        Ok(String::from_utf8(buf).unwrap())
+    }
+
+    fn option(procedural: Procedural, num_cols: u32, num_rows: u32) -> ProceduralAsciiArtOption {
+        let aspect_ratio =
+            SimpleAspectRatio::new(Some(num_cols), Some(num_rows), TermFit::None, false);
+        ProceduralAsciiArtOption::new(procedural, num_cols).with_aspect_ratio(aspect_ratio)
+    }
+
+    #[test]
+    fn test_rng() {
+        let mut rng = Rng::new(1234567);
+        // reference values of SplitMix64
+        assert_eq!(6457827717110365317, rng.next_u64());
+        assert_eq!(3203168211198807973, rng.next_u64());
+        assert!((0..1000).all(|_| (0.0..1.0).contains(&rng.next_f64())));
+    }
+
+    #[test]
+    fn test_automaton() -> Result<(), UnicodeArtError> {
+        let option = option(Procedural::Automaton(90), 15, 8).with_charset(" #");
+        let actual = render(&option)?;
+        assert_eq!(
+            r########"       #       
+      # #      
+     #   #     
+    # # # #    
+   #       #   
+  # #     # #  
+ #   #   #   # 
+# # # # # # # #
+"########,
+            actual
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_maze() -> Result<(), UnicodeArtError> {
+        let option = option(Procedural::Maze, 21, 9)
+            .with_charset(" #")
+            .with_seed(42);
+        let actual = render(&option)?;
+        assert_eq!(
+            "#####################\n\
+             # #   #         #   #\n\
+             # # # ####### # ### #\n\
+             # # #     #   # #   #\n\
+             # # ##### # ### # ###\n\
+             #   # #   # #   #   #\n\
+             ##### # ### # ##### #\n\
+             #           #       #\n\
+             #####################\n",
+            actual
+        );
+        // the same seed draws the same maze
+        assert_eq!(actual, render(&option)?);
+        assert_ne!(actual, render(&option.with_seed(7))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_life() {
+        // a blinker turns back after two generations, a block stays
+        let mut blinker = vec![false; 25];
+        blinker[11..14].fill(true);
+        let turned = next_generation(&blinker, 5, 5);
+        assert_ne!(blinker, turned);
+        assert_eq!(blinker, next_generation(&turned, 5, 5));
+        let mut block = vec![false; 16];
+        for i in [5, 6, 9, 10] {
+            block[i] = true;
+        }
+        assert_eq!(block, next_generation(&block, 4, 4));
+    }
+
+    #[test]
+    fn test_simplex() {
+        let noise = GradientNoise::new(&mut Rng::new(DEFAULT_SEED));
+        let samples: Vec<f64> = (0..400)
+            .map(|i| noise.simplex(i as f64 * 0.37, i as f64 * 0.11))
+            .collect();
+        assert!(samples.iter().all(|value| (-0.6..=0.6).contains(value)));
+        assert!(samples.iter().any(|&value| value > 0.2));
+        assert!(samples.iter().any(|&value| value < -0.2));
+        // a lattice point is zero for its own gradient and out of reach of the rest
+        assert_eq!(0.0, noise.simplex(0.0, 0.0));
+    }
+
+    #[test]
+    fn test_seeded_patterns() -> Result<(), UnicodeArtError> {
+        for procedural in [
+            Procedural::Noise,
+            Procedural::Simplex,
+            Procedural::Plasma,
+            Procedural::Life,
+        ] {
+            let option = option(procedural, 40, 10).with_seed(3);
+            let actual = render(&option)?;
+            assert_eq!(10, actual.lines().count());
+            assert!(actual.lines().all(|line| line.chars().count() == 40));
+            // more than a couple of the ramp's characters
+            let distinct = CHARSET_DENSITY
+                .chars()
+                .filter(|&ch| actual.contains(ch))
+                .count();
+            assert!(distinct >= 2, "{:?}\n{}", procedural, actual);
+            assert_eq!(actual, render(&option)?);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+use std::convert::Infallible;
+
+use embedded_graphics::mono_font::iso_8859_1::FONT_7X14;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+use embedded_graphics::prelude::{DrawTarget, OriginDimensions, Pixel, Point, Size};
+use embedded_graphics::text::{Baseline, Text};
+use embedded_graphics::Drawable;
+use image::{Rgb, Rgba, RgbaImage};
+
+use super::braille::{BRAILLE_BLANK, BRAILLE_DOTS};
+use super::grid::CellGrid;
+
+/// Pixel size of a cell, twice as tall as wide like a terminal cell.
+pub const CELL_WIDTH: u32 = 7;
+pub const CELL_HEIGHT: u32 = 14;
+/// Colors used where the output leaves the terminal defaults
+pub const DEFAULT_FOREGROUND: Rgb<u8> = Rgb([204, 204, 204]);
+pub const DEFAULT_BACKGROUND: Rgb<u8> = Rgb([0, 0, 0]);
+
+struct Canvas<'a>(&'a mut RgbaImage);
+
+impl OriginDimensions for Canvas<'_> {
+    fn size(&self) -> Size {
+        Size::new(self.0.width(), self.0.height())
+    }
+}
+
+impl DrawTarget for Canvas<'_> {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            let (x, y) = (point.x as u32, point.y as u32);
+            if point.x >= 0 && point.y >= 0 && x < self.0.width() && y < self.0.height() {
+                self.0
+                    .put_pixel(x, y, Rgba([color.r(), color.g(), color.b(), 255]));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Quadrants covered by a block element: upper left, upper right, lower left
+/// and lower right.
+fn block_quadrants(ch: char) -> Option<[bool; 4]> {
+    let quadrants = match ch {
+        '█' => [true, true, true, true],
+        '▀' => [true, true, false, false],
+        '▄' => [false, false, true, true],
+        '▌' => [true, false, true, false],
+        '▐' => [false, true, false, true],
+        '▖' => [false, false, true, false],
+        '▗' => [false, false, false, true],
+        '▘' => [true, false, false, false],
+        '▙' => [true, false, true, true],
+        '▚' => [true, false, false, true],
+        '▛' => [true, true, true, false],
+        '▜' => [true, true, false, true],
+        '▝' => [false, true, false, false],
+        '▞' => [false, true, true, false],
+        '▟' => [false, true, true, true],
+        _ => return None,
+    };
+    Some(quadrants)
+}
+
+fn shade(ch: char) -> Option<f32> {
+    match ch {
+        '░' => Some(0.25),
+        '▒' => Some(0.5),
+        '▓' => Some(0.75),
+        _ => None,
+    }
+}
+
+fn blend(fg: Rgb<u8>, bg: Rgb<u8>, amount: f32) -> Rgb<u8> {
+    let mix = |f: u8, b: u8| (f as f32 * amount + b as f32 * (1.0 - amount)).round() as u8;
+    Rgb([mix(fg[0], bg[0]), mix(fg[1], bg[1]), mix(fg[2], bg[2])])
+}
+
+fn fill(image: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: Rgb<u8>) {
+    for py in y..(y + height).min(image.height()) {
+        for px in x..(x + width).min(image.width()) {
+            image.put_pixel(px, py, Rgba([color[0], color[1], color[2], 255]));
+        }
+    }
+}
+
+fn draw_cell(image: &mut RgbaImage, x: u32, y: u32, ch: char, fg: Rgb<u8>, bg: Rgb<u8>) {
+    fill(image, x, y, CELL_WIDTH, CELL_HEIGHT, bg);
+    let (half_w, half_h) = (CELL_WIDTH / 2, CELL_HEIGHT / 2);
+    if let Some(quadrants) = block_quadrants(ch) {
+        for (i, covered) in quadrants.into_iter().enumerate() {
+            let (qx, qw) = match i % 2 {
+                0 => (0, half_w),
+                _ => (half_w, CELL_WIDTH - half_w),
+            };
+            let (qy, qh) = match i / 2 {
+                0 => (0, half_h),
+                _ => (half_h, CELL_HEIGHT - half_h),
+            };
+            if covered {
+                fill(image, x + qx, y + qy, qw, qh, fg);
+            }
+        }
+    } else if let Some(amount) = shade(ch) {
+        fill(image, x, y, CELL_WIDTH, CELL_HEIGHT, blend(fg, bg, amount));
+    } else if (BRAILLE_BLANK..BRAILLE_BLANK + 256).contains(&(ch as u32)) {
+        let bits = ch as u32 - BRAILLE_BLANK;
+        for (bit, (col, row)) in BRAILLE_DOTS.iter().enumerate() {
+            if bits & (1 << bit) != 0 {
+                fill(image, x + 1 + col * 3, y + 1 + row * 7 / 2, 2, 2, fg);
+            }
+        }
+    } else if ch != ' ' {
+        let style = MonoTextStyle::new(&FONT_7X14, Rgb888::new(fg[0], fg[1], fg[2]));
+        let mut buf = [0; 4];
+        let point = Point::new(x as i32, y as i32);
+        let _ = Text::with_baseline(ch.encode_utf8(&mut buf), point, style, Baseline::Top)
+            .draw(&mut Canvas(image));
+    }
+}
+
+/// Draw the cells with a built-in bitmap font. Block elements and braille
+/// patterns are drawn as shapes so that they tile seamlessly.
+pub fn rasterize(grid: &CellGrid) -> RgbaImage {
+    rasterize_with(grid, DEFAULT_FOREGROUND, DEFAULT_BACKGROUND)
+}
+
+/// Like [`rasterize`], with other colors where the output leaves the
+/// terminal defaults.
+pub fn rasterize_with(grid: &CellGrid, foreground: Rgb<u8>, background: Rgb<u8>) -> RgbaImage {
+    let width = grid.width().max(1) as u32 * CELL_WIDTH;
+    let height = grid.height().max(1) as u32 * CELL_HEIGHT;
+    let mut image = RgbaImage::new(width, height);
+    fill(&mut image, 0, 0, width, height, background);
+    for (row, cells) in grid.rows().enumerate() {
+        for (col, cell) in cells.iter().enumerate() {
+            // only the background of the right half of a wide character
+            let ch = match cell.is_wide_tail() {
+                true => ' ',
+                false => cell.ch,
+            };
+            draw_cell(
+                &mut image,
+                col as u32 * CELL_WIDTH,
+                row as u32 * CELL_HEIGHT,
+                ch,
+                cell.fg.unwrap_or(foreground),
+                cell.bg.unwrap_or(background),
+            );
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rasterize() {
+        let grid = CellGrid::parse("\x1B[38;2;255;0;0m█▀\x1B[0mA⠁\n");
+        let image = rasterize(&grid);
+        assert_eq!((4 * CELL_WIDTH, CELL_HEIGHT), image.dimensions());
+        let red = Rgba([255, 0, 0, 255]);
+        let black = Rgba([0, 0, 0, 255]);
+        assert!((0..CELL_WIDTH).all(|x| (0..CELL_HEIGHT).all(|y| *image.get_pixel(x, y) == red)));
+        // upper half block
+        assert_eq!(red, *image.get_pixel(CELL_WIDTH, 0));
+        assert_eq!(black, *image.get_pixel(CELL_WIDTH, CELL_HEIGHT - 1));
+        // a glyph leaves some of the cell empty
+        let glyph: Vec<_> = (2 * CELL_WIDTH..3 * CELL_WIDTH)
+            .flat_map(|x| (0..CELL_HEIGHT).map(move |y| (x, y)))
+            .map(|(x, y)| *image.get_pixel(x, y))
+            .collect();
+        assert!(glyph.contains(&black) && glyph.iter().any(|p| *p != black));
+        // braille dot 1 in the upper left corner
+        let x = 3 * CELL_WIDTH;
+        assert_ne!(black, *image.get_pixel(x + 1, 1));
+        assert_eq!(black, *image.get_pixel(x + 5, 12));
+    }
+}
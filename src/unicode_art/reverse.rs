@@ -0,0 +1,232 @@
+use std::str::FromStr;
+
+use image::{DynamicImage, Rgb, Rgba, RgbaImage};
+
+use super::ansiart::{from_cp437, EOF};
+use super::braille::{BRAILLE_BLANK, BRAILLE_DOTS};
+use super::error::UnicodeArtError;
+use super::grid::{Cell, CellGrid};
+use super::raster::rasterize_with;
+
+const TRANSPARENT: Rgba<u8> = Rgba([0, 0, 0, 0]);
+// the renderers pick dense characters and raised dots for dark pixels, so
+// default colours are dark ink on paper unless the output was inverted
+const DARK: Rgb<u8> = Rgb([0, 0, 0]);
+const LIGHT: Rgb<u8> = Rgb([255, 255, 255]);
+
+/// How cells are turned back into pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReverseMode {
+    /// Block or braille when every cell allows it, glyphs otherwise
+    Auto,
+    /// One pixel per half block, as written by `BlockUnicodeArt`
+    Block,
+    /// One pixel per dot, as written by `BrailleAsciiArt`
+    Braille,
+    /// Every cell drawn with the built-in font
+    Glyph,
+}
+
+impl FromStr for ReverseMode {
+    type Err = UnicodeArtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "block" => Ok(Self::Block),
+            "braille" => Ok(Self::Braille),
+            "glyph" => Ok(Self::Glyph),
+            _ => Err(UnicodeArtError::InvalidArgumentError(format!(
+                "unknown reverse mode {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Text of an ANSI file: UTF-8 when it is valid, code page 437 otherwise.
+/// A SAUCE record after the EOF marker is dropped and CR LF becomes LF.
+pub fn decode_ansi_bytes(bytes: &[u8]) -> String {
+    let bytes = match bytes.iter().position(|&b| b == EOF) {
+        Some(end) => &bytes[..end],
+        None => bytes,
+    };
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| from_cp437(b)).collect(),
+    };
+    text.replace("\r\n", "\n")
+}
+
+fn is_block(ch: char) -> bool {
+    matches!(ch, ' ' | '▀' | '▄' | '█')
+}
+
+fn is_braille(ch: char) -> bool {
+    ch == ' ' || (BRAILLE_BLANK..BRAILLE_BLANK + 256).contains(&(ch as u32))
+}
+
+fn cells(grid: &CellGrid) -> impl Iterator<Item = (usize, usize, Cell)> + '_ {
+    (0..grid.height())
+        .flat_map(move |row| (0..grid.width()).map(move |col| (row, col, grid.get(row, col))))
+}
+
+fn rgba(color: Rgb<u8>) -> Rgba<u8> {
+    Rgba([color[0], color[1], color[2], 255])
+}
+
+/// Two pixels stacked in every cell, default colours are transparent.
+fn from_blocks(grid: &CellGrid) -> RgbaImage {
+    let mut image =
+        RgbaImage::from_pixel(grid.width() as u32, grid.height() as u32 * 2, TRANSPARENT);
+    for (row, col, cell) in cells(grid) {
+        let fg = cell.fg.map_or(TRANSPARENT, rgba);
+        let bg = cell.bg.map_or(TRANSPARENT, rgba);
+        let (upper, lower) = match cell.ch {
+            '▀' => (fg, bg),
+            '▄' => (bg, fg),
+            '█' => (fg, fg),
+            _ => (bg, bg),
+        };
+        let (x, y) = (col as u32, row as u32 * 2);
+        image.put_pixel(x, y, upper);
+        image.put_pixel(x, y + 1, lower);
+    }
+    image
+}
+
+/// 2x4 pixels in every cell, raised dots in the foreground colour.
+fn from_braille(grid: &CellGrid, ink: Rgb<u8>, paper: Rgb<u8>) -> RgbaImage {
+    let mut image = RgbaImage::new(grid.width() as u32 * 2, grid.height() as u32 * 4);
+    for (row, col, cell) in cells(grid) {
+        let bits = (cell.ch as u32).saturating_sub(BRAILLE_BLANK);
+        let ink = rgba(cell.fg.unwrap_or(ink));
+        let paper = rgba(cell.bg.unwrap_or(paper));
+        for (bit, (x, y)) in BRAILLE_DOTS.iter().enumerate() {
+            let raised = cell.ch != ' ' && bits & (1 << bit) != 0;
+            let color = if raised { ink } else { paper };
+            image.put_pixel(col as u32 * 2 + x, row as u32 * 4 + y, color);
+        }
+    }
+    image
+}
+
+/// Reconstruct a bitmap from cells. Block and braille output convert back
+/// exactly to the thumbnail the renderer sampled; any other text is drawn
+/// with the built-in font. `is_invert` matches output rendered with
+/// `--invert`, light on dark.
+pub fn grid_to_image(
+    grid: &CellGrid,
+    mode: ReverseMode,
+    is_invert: bool,
+) -> Result<DynamicImage, UnicodeArtError> {
+    let all = |accept: fn(char) -> bool| cells(grid).all(|(_, _, cell)| accept(cell.ch));
+    let mode = match mode {
+        ReverseMode::Auto if all(is_block) => ReverseMode::Block,
+        ReverseMode::Auto if all(is_braille) => ReverseMode::Braille,
+        ReverseMode::Auto => ReverseMode::Glyph,
+        ReverseMode::Block if !all(is_block) => {
+            return Err(UnicodeArtError::InvalidArgumentError(
+                "text has characters other than half blocks".to_string(),
+            ))
+        }
+        ReverseMode::Braille if !all(is_braille) => {
+            return Err(UnicodeArtError::InvalidArgumentError(
+                "text has characters other than braille".to_string(),
+            ))
+        }
+        mode => mode,
+    };
+    let (ink, paper) = match is_invert {
+        true => (LIGHT, DARK),
+        false => (DARK, LIGHT),
+    };
+    let image = match mode {
+        ReverseMode::Block => from_blocks(grid),
+        ReverseMode::Braille => from_braille(grid, ink, paper),
+        _ => rasterize_with(grid, ink, paper),
+    };
+    Ok(DynamicImage::ImageRgba8(image))
+}
+
+/// Parse ANSI text, as written by the renderers or an ANSI art file, back
+/// into an image.
+pub fn ansi_to_image(
+    bytes: &[u8],
+    mode: ReverseMode,
+    is_invert: bool,
+) -> Result<DynamicImage, UnicodeArtError> {
+    grid_to_image(&CellGrid::parse(&decode_ansi_bytes(bytes)), mode, is_invert)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::io::Reader;
+    use image::GenericImageView;
+
+    use super::super::alpha::Transparent;
+    use super::super::aspect_ratio::{SimpleAspectRatio, TermFit};
+    use super::super::block::BlockUnicodeArtOption;
+    use super::super::braille::BrailleAsciiArtOption;
+    use super::super::UnicodeArtOption;
+    use super::*;
+
+    fn render(option: &dyn UnicodeArtOption, image: &DynamicImage) -> Vec<u8> {
+        let mut buf = Vec::new();
+        option
+            .new_unicode_art(image)
+            .and_then(|art| art.write_all(&mut buf))
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_block_oracle() -> Result<(), UnicodeArtError> {
+        let image = Reader::open("tests/support/test_gundam.png")?.decode()?;
+        let aspect_ratio = SimpleAspectRatio::new(Some(24), Some(16), TermFit::None, false);
+        let option = BlockUnicodeArtOption::new(24, true).with_aspect_ratio(aspect_ratio);
+        let reversed = ansi_to_image(&render(&option, &image), ReverseMode::Auto, false)?;
+        let thumbnail = image.thumbnail_exact(24, 32).to_rgba8();
+        assert_eq!((24, 32), reversed.dimensions());
+        for (x, y, pixel) in thumbnail.enumerate_pixels() {
+            match pixel.is_transparent() {
+                true => assert_eq!(0, reversed.get_pixel(x, y)[3]),
+                false => assert_eq!(&pixel.0[..3], &reversed.get_pixel(x, y).0[..3]),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_braille_oracle() -> Result<(), UnicodeArtError> {
+        let image = Reader::open("tests/support/test_gundam.png")?.decode()?;
+        let aspect_ratio = SimpleAspectRatio::new(Some(20), Some(10), TermFit::None, false);
+        let option =
+            BrailleAsciiArtOption::new(20, 127, false, false).with_aspect_ratio(aspect_ratio);
+        let reversed = ansi_to_image(&render(&option, &image), ReverseMode::Auto, false)?;
+        let thumbnail = image.thumbnail_exact(40, 40).to_rgba8();
+        assert_eq!((40, 40), reversed.dimensions());
+        for (x, y, pixel) in thumbnail.enumerate_pixels() {
+            let grey = (pixel[0] as u32 + pixel[1] as u32 + pixel[2] as u32) / 3;
+            let dark = !pixel.is_transparent() && grey < 127;
+            assert_eq!(dark, reversed.get_pixel(x, y)[0] == 0, "({}, {})", x, y);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_ansi_art_file() -> Result<(), UnicodeArtError> {
+        // bright red upper half over blue, then the SAUCE record
+        let image = ansi_to_image(
+            b"\x1B[0;1;31;44m\xDF\r\n\x1B[0m\x1ASAUCE00",
+            ReverseMode::Auto,
+            false,
+        )?;
+        assert_eq!((1, 2), image.dimensions());
+        assert_eq!(Rgba([0xFF, 0x55, 0x55, 255]), image.get_pixel(0, 0));
+        assert_eq!(Rgba([0x00, 0x00, 0xAA, 255]), image.get_pixel(0, 1));
+        assert!(ansi_to_image(b"A", ReverseMode::Block, false).is_err());
+        assert_eq!(ReverseMode::Glyph, "glyph".parse::<ReverseMode>().unwrap());
+        Ok(())
+    }
+}
@@ -0,0 +1,439 @@
+use std::io::{self, BufRead, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use image::{DynamicImage, RgbImage};
+
+use super::animation::Frame;
+use super::delta::DeltaEncoder;
+use super::error::UnicodeArtError;
+use super::UnicodeArtOption;
+
+pub const DEFAULT_FRAME_RATE: f64 = 25.0;
+/// Larger frames are taken for a broken header rather than allocated, 8K video
+/// fits well within
+const MAX_FRAME_PIXELS: u64 = 8192 * 8192;
+
+/// A live sequence of video frames, as opposed to the frames of a single
+/// animated image which are all decoded upfront.
+pub trait FrameStream {
+    /// Frames per second
+    fn frame_rate(&self) -> f64;
+    /// Next frame, or `None` at the end of the stream
+    fn next_frame(&mut self) -> Result<Option<DynamicImage>, UnicodeArtError>;
+}
+
+fn invalid_data(msg: String) -> UnicodeArtError {
+    UnicodeArtError::IoError(io::Error::new(io::ErrorKind::InvalidData, msg))
+}
+
+fn check_frame_size(width: u32, height: u32) -> Result<(), UnicodeArtError> {
+    match width as u64 * height as u64 > MAX_FRAME_PIXELS {
+        true => Err(invalid_data(format!(
+            "frame size {}x{} too large",
+            width, height
+        ))),
+        false => Ok(()),
+    }
+}
+
+/// Fill `buf` completely, or report a clean end of stream when no byte at
+/// all could be read.
+fn read_frame_data<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool, UnicodeArtError> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(invalid_data("truncated frame".to_string())),
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(true)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Chroma {
+    C420,
+    C422,
+    C444,
+    Mono,
+}
+
+/// YUV4MPEG2 stream, as written by `ffmpeg -f yuv4mpegpipe`.
+pub struct Y4mStream<R: BufRead> {
+    reader: R,
+    width: u32,
+    height: u32,
+    frame_rate: f64,
+    chroma: Chroma,
+    buf: Vec<u8>,
+}
+
+impl<R: BufRead> Y4mStream<R> {
+    pub fn new(mut reader: R) -> Result<Self, UnicodeArtError> {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let mut params = header.trim_end().split(' ');
+        if params.next() != Some("YUV4MPEG2") {
+            return Err(invalid_data("missing YUV4MPEG2 signature".to_string()));
+        }
+        let (mut width, mut height) = (0, 0);
+        let mut frame_rate = DEFAULT_FRAME_RATE;
+        let mut chroma = Chroma::C420;
+        for param in params.filter(|p| !p.is_empty()) {
+            let mut chars = param.chars();
+            let key = chars.next();
+            let value = chars.as_str();
+            let bad = || invalid_data(format!("invalid y4m parameter {}", param));
+            match key {
+                Some('W') => width = value.parse().map_err(|_| bad())?,
+                Some('H') => height = value.parse().map_err(|_| bad())?,
+                Some('F') => {
+                    let (numer, denom) = value.split_once(':').ok_or_else(bad)?;
+                    let numer: f64 = numer.parse().map_err(|_| bad())?;
+                    let denom: f64 = denom.parse().map_err(|_| bad())?;
+                    if numer > 0.0 && denom > 0.0 {
+                        frame_rate = numer / denom;
+                    }
+                }
+                Some('C') => {
+                    chroma = match value {
+                        "420" | "420jpeg" | "420paldv" | "420mpeg2" => Chroma::C420,
+                        "422" => Chroma::C422,
+                        "444" => Chroma::C444,
+                        "mono" => Chroma::Mono,
+                        _ => return Err(invalid_data(format!("unsupported colorspace {}", value))),
+                    }
+                }
+                // interlacing, pixel aspect and extensions don't affect decoding
+                _ => {}
+            }
+        }
+        if width == 0 || height == 0 {
+            return Err(invalid_data("missing frame size".to_string()));
+        }
+        check_frame_size(width, height)?;
+        let mut stream = Self {
+            reader,
+            width,
+            height,
+            frame_rate,
+            chroma,
+            buf: Vec::new(),
+        };
+        let frame_len = stream
+            .frame_len()
+            .ok_or_else(|| invalid_data(format!("frame size {}x{} too large", width, height)))?;
+        stream.buf = vec![0; frame_len];
+        Ok(stream)
+    }
+
+    fn chroma_size(&self) -> (u32, u32) {
+        match self.chroma {
+            Chroma::C420 => (self.width.div_ceil(2), self.height.div_ceil(2)),
+            Chroma::C422 => (self.width.div_ceil(2), self.height),
+            Chroma::C444 => (self.width, self.height),
+            Chroma::Mono => (0, 0),
+        }
+    }
+
+    /// Pixels per chroma sample horizontally and vertically.
+    fn subsampling(&self) -> (u32, u32) {
+        match self.chroma {
+            Chroma::C420 => (2, 2),
+            Chroma::C422 => (2, 1),
+            Chroma::C444 | Chroma::Mono => (1, 1),
+        }
+    }
+
+    /// Bytes of a frame, `None` when that doesn't fit in memory.
+    fn frame_len(&self) -> Option<usize> {
+        let (cw, ch) = self.chroma_size();
+        let luma_len = (self.width as usize).checked_mul(self.height as usize)?;
+        let chroma_len = (cw as usize).checked_mul(ch as usize)?.checked_mul(2)?;
+        luma_len.checked_add(chroma_len)
+    }
+
+    fn to_rgb(&self) -> RgbImage {
+        let (cw, ch) = self.chroma_size();
+        let (width, cw) = (self.width as usize, cw as usize);
+        let luma_len = width * self.height as usize;
+        let chroma_len = cw * ch as usize;
+        let (luma, chroma) = self.buf.split_at(luma_len);
+        let (cb, cr) = chroma.split_at(chroma_len);
+        let (sx, sy) = self.subsampling();
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            let l = luma[y as usize * width + x as usize];
+            let (u, v) = match self.chroma {
+                Chroma::Mono => (128, 128),
+                _ => {
+                    let i = (y / sy) as usize * cw + (x / sx) as usize;
+                    (cb[i], cr[i])
+                }
+            };
+            yuv_to_rgb(l, u, v)
+        })
+    }
+}
+
+/// BT.601 limited range, the default of ffmpeg's yuv4mpegpipe muxer.
+fn yuv_to_rgb(y: u8, u: u8, v: u8) -> image::Rgb<u8> {
+    let c = 1.164 * (y as f32 - 16.0);
+    let d = u as f32 - 128.0;
+    let e = v as f32 - 128.0;
+    let clamp = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+    image::Rgb([
+        clamp(c + 1.596 * e),
+        clamp(c - 0.392 * d - 0.813 * e),
+        clamp(c + 2.017 * d),
+    ])
+}
+
+impl<R: BufRead> FrameStream for Y4mStream<R> {
+    fn frame_rate(&self) -> f64 {
+        self.frame_rate
+    }
+
+    fn next_frame(&mut self) -> Result<Option<DynamicImage>, UnicodeArtError> {
+        let mut marker = String::new();
+        if self.reader.read_line(&mut marker)? == 0 {
+            return Ok(None);
+        }
+        if !marker.starts_with("FRAME") {
+            return Err(invalid_data("missing FRAME marker".to_string()));
+        }
+        if !read_frame_data(&mut self.reader, &mut self.buf)? {
+            return Err(invalid_data("truncated frame".to_string()));
+        }
+        Ok(Some(DynamicImage::ImageRgb8(self.to_rgb())))
+    }
+}
+
+/// Headerless packed `rgb24` frames of a known size, e.g.
+/// `ffmpeg -f rawvideo -pix_fmt rgb24`.
+pub struct RawRgbStream<R: Read> {
+    reader: R,
+    width: u32,
+    height: u32,
+    frame_rate: f64,
+}
+
+impl<R: Read> RawRgbStream<R> {
+    pub fn new(reader: R, width: u32, height: u32, frame_rate: f64) -> Self {
+        Self {
+            reader,
+            width,
+            height,
+            frame_rate,
+        }
+    }
+}
+
+impl<R: Read> FrameStream for RawRgbStream<R> {
+    fn frame_rate(&self) -> f64 {
+        self.frame_rate
+    }
+
+    fn next_frame(&mut self) -> Result<Option<DynamicImage>, UnicodeArtError> {
+        check_frame_size(self.width, self.height)?;
+        let mut buf = vec![0; self.width as usize * self.height as usize * 3];
+        if !read_frame_data(&mut self.reader, &mut buf)? {
+            return Ok(None);
+        }
+        let image = RgbImage::from_raw(self.width, self.height, buf).expect("frame size");
+        Ok(Some(DynamicImage::ImageRgb8(image)))
+    }
+}
+
+/// Render up to `max_frames` frames of a stream, each as soon as it is read
+/// and lasting one frame period.
+pub fn render_stream(
+    stream: &mut dyn FrameStream,
+    option: &dyn UnicodeArtOption,
+    prepare: &dyn Fn(DynamicImage) -> Result<DynamicImage, UnicodeArtError>,
+    max_frames: usize,
+) -> Result<Vec<Frame<String>>, UnicodeArtError> {
+    let frame_time = Duration::from_secs_f64(1.0 / stream.frame_rate());
+    let mut frames = Vec::new();
+    while frames.len() < max_frames {
+        let Some(image) = stream.next_frame()? else {
+            break;
+        };
+        let mut buf = Vec::new();
+        option
+            .new_unicode_art(&prepare(image)?)?
+            .write_all(&mut buf)?;
+        let content = String::from_utf8_lossy(&buf).into_owned();
+        frames.push(Frame::new(content, frame_time));
+    }
+    Ok(frames)
+}
+
+/// Renders a stream frame by frame. In real time mode the output is paced to
+/// the stream's frame rate, and frames are dropped while the terminal can't
+/// keep up; otherwise every frame is written one after another.
+pub struct StreamPlayer {
+    realtime: bool,
+}
+
+impl StreamPlayer {
+    pub fn new(realtime: bool) -> Self {
+        Self { realtime }
+    }
+
+    /// Returns the number of dropped frames.
+    pub fn play(
+        &self,
+        stream: &mut dyn FrameStream,
+        option: &dyn UnicodeArtOption,
+        prepare: &dyn Fn(DynamicImage) -> Result<DynamicImage, UnicodeArtError>,
+        writer: &mut dyn Write,
+    ) -> Result<usize, UnicodeArtError> {
+        let frame_time = Duration::from_secs_f64(1.0 / stream.frame_rate());
+        let start = Instant::now();
+        let mut dropped = 0;
+        let mut index = 0u32;
+        let mut encoder = DeltaEncoder::default();
+        while let Some(image) = stream.next_frame()? {
+            let due = start + frame_time * index;
+            index += 1;
+            // already later than the next frame's slot
+            if self.realtime && Instant::now() > due + frame_time {
+                dropped += 1;
+                continue;
+            }
+            let mut buf = Vec::new();
+            option
+                .new_unicode_art(&prepare(image)?)?
+                .write_all(&mut buf)?;
+            if self.realtime {
+                let frame = String::from_utf8_lossy(&buf);
+                write!(writer, "{}", encoder.encode(&frame))?;
+            } else {
+                if index > 1 {
+                    writeln!(writer)?;
+                }
+                writer.write_all(&buf)?;
+            }
+            writer.flush()?;
+            if self.realtime {
+                let next = start + frame_time * index;
+                let now = Instant::now();
+                if next > now {
+                    thread::sleep(next - now);
+                }
+            }
+        }
+        Ok(dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unicode_art::classic::ClassicAsciiArtOption;
+    use image::GenericImageView;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_y4m_444() -> Result<(), UnicodeArtError> {
+        let mut data = b"YUV4MPEG2 W2 H1 F30000:1001 Ip A1:1 C444\nFRAME\n".to_vec();
+        // Y plane, U plane, V plane
+        data.extend([16, 235, 128, 128, 128, 128]);
+        data.extend(b"FRAME Ixyz\n");
+        data.extend([81, 145, 90, 54, 240, 34]);
+        let mut stream = Y4mStream::new(Cursor::new(data))?;
+        assert!((stream.frame_rate() - 29.97).abs() < 0.01);
+
+        let frame = stream.next_frame()?.unwrap();
+        assert_eq!((2, 1), frame.dimensions());
+        assert_eq!([0, 0, 0], frame.to_rgb8().get_pixel(0, 0).0);
+        assert_eq!([255, 255, 255], frame.to_rgb8().get_pixel(1, 0).0);
+
+        // pure red and green, give or take rounding of the BT.601 coefficients
+        let frame = stream.next_frame()?.unwrap().to_rgb8();
+        let close = |a: [u8; 3], b: [u8; 3]| a.iter().zip(b).all(|(a, b)| a.abs_diff(b) <= 2);
+        assert!(close([255, 0, 0], frame.get_pixel(0, 0).0));
+        assert!(close([0, 255, 0], frame.get_pixel(1, 0).0));
+        assert!(stream.next_frame()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_y4m_420_odd_size() -> Result<(), UnicodeArtError> {
+        let mut data = b"YUV4MPEG2 W3 H3 F25:1\nFRAME\n".to_vec();
+        data.extend([235; 9]);
+        data.extend([128; 8]);
+        let mut stream = Y4mStream::new(Cursor::new(data))?;
+        let frame = stream.next_frame()?.unwrap().to_rgb8();
+        assert!(frame.pixels().all(|p| p.0 == [255, 255, 255]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_y4m_420_odd_chroma() -> Result<(), UnicodeArtError> {
+        let mut data = b"YUV4MPEG2 W3 H3 C420jpeg\nFRAME\n".to_vec();
+        data.extend([126; 9]);
+        // 2x2 chroma samples, only the right column is red
+        data.extend([128; 4]);
+        data.extend([128, 240, 128, 240]);
+        let mut stream = Y4mStream::new(Cursor::new(data))?;
+        let frame = stream.next_frame()?.unwrap().to_rgb8();
+        let is_grey = |x, y| {
+            let [r, g, b] = frame.get_pixel(x, y).0;
+            r == g && g == b
+        };
+        for y in 0..3 {
+            assert!(is_grey(0, y) && is_grey(1, y), "row {}", y);
+            assert!(!is_grey(2, y), "row {}", y);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_y4m_invalid() {
+        assert!(Y4mStream::new(Cursor::new(b"RIFF\n".to_vec())).is_err());
+        assert!(Y4mStream::new(Cursor::new(b"YUV4MPEG2 W2 H2 Cp010\n".to_vec())).is_err());
+        assert!(Y4mStream::new(Cursor::new("YUV4MPEG2 W2 H2 é\n".as_bytes().to_vec())).is_ok());
+        let huge = format!("YUV4MPEG2 W{} H{}\n", u32::MAX, u32::MAX);
+        assert!(Y4mStream::new(Cursor::new(huge.into_bytes())).is_err());
+        // fits in memory arithmetic, but not in memory
+        assert!(Y4mStream::new(Cursor::new(b"YUV4MPEG2 W99999 H99999\n".to_vec())).is_err());
+        let mut raw = RawRgbStream::new(Cursor::new(Vec::new()), 99999, 99999, DEFAULT_FRAME_RATE);
+        assert!(raw.next_frame().is_err());
+        let mut stream = Y4mStream::new(Cursor::new(b"YUV4MPEG2 W2 H2\nFRAME\n\x10".to_vec()))
+            .expect("valid header");
+        assert!(stream.next_frame().is_err());
+    }
+
+    #[test]
+    fn test_render_stream() -> Result<(), UnicodeArtError> {
+        let data = vec![0, 0, 0, 255, 255, 255, 255, 255, 255];
+        let option = ClassicAsciiArtOption::new_level_10(1, false, false);
+        let mut stream = RawRgbStream::new(Cursor::new(data.clone()), 1, 1, DEFAULT_FRAME_RATE);
+        let frames = render_stream(&mut stream, &option, &Ok, usize::MAX)?;
+        let contents: Vec<_> = frames.iter().map(|frame| frame.content.as_str()).collect();
+        assert_eq!(vec!["@\n", " \n", " \n"], contents);
+        assert_eq!(Duration::from_millis(40), frames[0].delay);
+
+        // the rest of the stream isn't read
+        let mut stream = RawRgbStream::new(Cursor::new(data), 1, 1, DEFAULT_FRAME_RATE);
+        assert_eq!(1, render_stream(&mut stream, &option, &Ok, 1)?.len());
+        assert!(stream.next_frame()?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_play_raw() -> Result<(), UnicodeArtError> {
+        let data = vec![0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 255, 255];
+        let mut stream = RawRgbStream::new(Cursor::new(data), 2, 1, DEFAULT_FRAME_RATE);
+        let option = ClassicAsciiArtOption::new_level_10(2, false, false);
+        let mut buf = Vec::new();
+        let dropped = StreamPlayer::new(false).play(&mut stream, &option, &Ok, &mut buf)?;
+        assert_eq!(0, dropped);
+        assert_eq!("@@\n\n  \n", String::from_utf8(buf).unwrap());
+        Ok(())
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Read, Write};
 use std::thread;
 
+use super::aspect_ratio::{AspectRatio, SimpleAspectRatio, TermFit};
 use super::classic::ClassicAsciiArtOption;
 use super::error::UnicodeArtError;
 use super::{UnicodeArt, UnicodeArtOption};
@@ -9,19 +10,9 @@ use clap::lazy_static::lazy_static;
 use image::DynamicImage;
 use itertools::Itertools;
 
-// TODO: could be replace with lhs.abs_diff(rhs) later on.
-#[inline]
-fn abs_diff(slf: u32, other: u32) -> u32 {
-    if slf < other {
-        other - slf
-    } else {
-        slf - other
-    }
-}
-
 #[derive(Debug)]
 pub struct SubpixelUnicodeArtOption<'a> {
-    num_cols: u32,
+    aspect_ratio: SimpleAspectRatio,
     letters: &'a HashMap<u32, [u8; 9]>,
     grid_size: usize,
     is_invert: bool,
@@ -35,19 +26,24 @@ pub struct SubpixelUnicodeArt<'a> {
 impl<'a> SubpixelUnicodeArtOption<'a> {
     pub fn new(num_cols: u32, is_invert: bool) -> Self {
         Self {
-            num_cols,
+            aspect_ratio: SimpleAspectRatio::new_auto_height(num_cols, TermFit::None, false),
             letters: &LETTER3,
             grid_size: 3,
             is_invert,
         }
     }
+
+    pub fn with_aspect_ratio(mut self, aspect_ratio: SimpleAspectRatio) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
 }
 
 impl UnicodeArtOption for SubpixelUnicodeArtOption<'static> {
     fn new_unicode_art<'a>(
         &'a self,
         image: &'a DynamicImage,
-    ) -> Result<Box<(dyn UnicodeArt + 'a)>, UnicodeArtError> {
+    ) -> Result<Box<dyn UnicodeArt + 'a>, UnicodeArtError> {
         Ok(Box::new(SubpixelUnicodeArt {
             options: self,
             image,
@@ -79,8 +75,9 @@ impl<'a> SubpixelUnicodeArt<'a> {
                 if block.len() < total_size {
                     block.push_str(&"0000000000"[0..total_size - block.len()]);
                 }
+                // transparent cells are rendered as spaces, i.e. no coverage
                 let block_final: Vec<u32> =
-                    block.chars().map(|b| b.to_digit(10).unwrap()).collect();
+                    block.chars().map(|b| b.to_digit(10).unwrap_or(0)).collect();
                 if let Some(letter) = self.distance(&block_final) {
                     write!(output, "{}", char::from_u32(letter).unwrap())?;
                     // write!(output, "&#{};", letter)?;
@@ -91,14 +88,14 @@ impl<'a> SubpixelUnicodeArt<'a> {
         Ok(())
     }
 
-    fn distance<'b>(&self, y: &'b Vec<u32>) -> Option<u32> {
+    fn distance(&self, y: &[u32]) -> Option<u32> {
         let mut distances = HashMap::new();
 
         for (&key, a) in self.options.letters.iter() {
             let mut cur_distance = 0;
             for (index, &aa) in a.iter().enumerate() {
                 let bb = y[index];
-                cur_distance += abs_diff(aa as u32, bb);
+                cur_distance += (aa as u32).abs_diff(bb);
             }
             distances.insert(key, cur_distance);
         }
@@ -114,17 +111,29 @@ impl<'a> SubpixelUnicodeArt<'a> {
 impl<'a> UnicodeArt for SubpixelUnicodeArt<'a> {
     fn write_all(&self, writer: &mut dyn Write) -> Result<(), UnicodeArtError> {
         let (mut read, mut write) = pipe::pipe();
-        let num_cols = self.options.num_cols;
+        let (num_cols, num_rows) = self
+            .options
+            .aspect_ratio
+            .calculate(self.image.width(), self.image.height());
+        let grid_size = self.options.grid_size as u32;
         let is_invert = self.options.is_invert;
         let image = self.image.clone();
         let handler = thread::spawn(move || {
-            let _ = ClassicAsciiArtOption::new_level_4(num_cols * 3, false, is_invert)
+            // every cell is a grid of classic sub-cells
+            let aspect_ratio = SimpleAspectRatio::new(
+                Some(num_cols * grid_size),
+                Some(num_rows * grid_size),
+                TermFit::None,
+                false,
+            );
+            let _ = ClassicAsciiArtOption::new_level_4(num_cols, false, is_invert)
+                .with_aspect_ratio(aspect_ratio)
                 .new_unicode_art(&image)
                 .unwrap()
                 .write_all(&mut write);
         });
         let _ = self.convert(&mut read, writer);
-        let _ = handler.join().expect("error");
+        handler.join().expect("error");
         Ok(())
     }
 }
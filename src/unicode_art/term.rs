@@ -0,0 +1,39 @@
+use std::env;
+use std::io::{stdout, IsTerminal};
+
+/// Whether stdout is attached to a terminal rather than a pipe or file.
+pub fn is_terminal() -> bool {
+    stdout().is_terminal()
+}
+
+/// Size of the terminal attached to stdout as `(columns, rows)`.
+///
+/// Returns `None` when stdout is not a TTY, e.g. when the output is piped
+/// into a file, so that callers fall back to the requested dimensions.
+#[cfg(unix)]
+pub fn terminal_size() -> Option<(u32, u32)> {
+    if !is_terminal() {
+        return None;
+    }
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let ret = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    if ret == 0 && size.ws_col > 0 && size.ws_row > 0 {
+        Some((size.ws_col as u32, size.ws_row as u32))
+    } else {
+        env_size()
+    }
+}
+
+#[cfg(not(unix))]
+pub fn terminal_size() -> Option<(u32, u32)> {
+    if !is_terminal() {
+        return None;
+    }
+    env_size()
+}
+
+/// `COLUMNS` and `LINES` as exported by most shells.
+fn env_size() -> Option<(u32, u32)> {
+    let read = |name| env::var(name).ok()?.parse::<u32>().ok().filter(|&v| v > 0);
+    Some((read("COLUMNS")?, read("LINES")?))
+}
@@ -0,0 +1,232 @@
+use std::io::{BufRead, Seek};
+use std::str::FromStr;
+
+use image::DynamicImage;
+
+use super::error::UnicodeArtError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rotation {
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl FromStr for Rotation {
+    type Err = UnicodeArtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "90" => Ok(Rotation::Rotate90),
+            "180" => Ok(Rotation::Rotate180),
+            "270" => Ok(Rotation::Rotate270),
+            _ => Err(UnicodeArtError::UnsupportError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flip {
+    Horizontal,
+    Vertical,
+}
+
+impl FromStr for Flip {
+    type Err = UnicodeArtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "h" => Ok(Flip::Horizontal),
+            "v" => Ok(Flip::Vertical),
+            _ => Err(UnicodeArtError::UnsupportError),
+        }
+    }
+}
+
+/// Region of interest, in pixels of the upright image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for Crop {
+    type Err = UnicodeArtError;
+
+    /// Parse `x,y,w,h`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| UnicodeArtError::UnsupportError)?;
+        match values[..] {
+            [x, y, width, height] if width > 0 && height > 0 => Ok(Crop {
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => Err(UnicodeArtError::UnsupportError),
+        }
+    }
+}
+
+/// Geometric transforms applied before rendering, in the order
+/// EXIF orientation, crop, rotate, flip.
+#[derive(Debug, Default, Clone)]
+pub struct Transform {
+    pub(crate) orientation: Option<u32>,
+    pub(crate) crop: Option<Crop>,
+    pub(crate) rotate: Option<Rotation>,
+    pub(crate) flip: Option<Flip>,
+}
+
+impl Transform {
+    pub fn apply(&self, image: DynamicImage) -> Result<DynamicImage, UnicodeArtError> {
+        let mut img = match self.orientation {
+            Some(orientation) => orient(image, orientation),
+            None => image,
+        };
+        if let Some(crop) = self.crop {
+            if crop.x >= img.width() || crop.y >= img.height() {
+                return Err(UnicodeArtError::InvalidArgumentError(format!(
+                    "crop origin {},{} is outside the {}x{} image",
+                    crop.x,
+                    crop.y,
+                    img.width(),
+                    img.height()
+                )));
+            }
+            let width = crop.width.min(img.width() - crop.x);
+            let height = crop.height.min(img.height() - crop.y);
+            img = img.crop_imm(crop.x, crop.y, width, height);
+        }
+        img = match self.rotate {
+            Some(Rotation::Rotate90) => img.rotate90(),
+            Some(Rotation::Rotate180) => img.rotate180(),
+            Some(Rotation::Rotate270) => img.rotate270(),
+            None => img,
+        };
+        img = match self.flip {
+            Some(Flip::Horizontal) => img.fliph(),
+            Some(Flip::Vertical) => img.flipv(),
+            None => img,
+        };
+        Ok(img)
+    }
+}
+
+/// Read the EXIF orientation tag (1-8) of a JPEG, TIFF, PNG, HEIF or WebP
+/// stream. Returns `None` when the stream carries no orientation.
+pub fn exif_orientation<R: BufRead + Seek>(reader: &mut R) -> Option<u32> {
+    let exif = exif::Reader::new().read_from_container(reader).ok()?;
+    exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?
+        .value
+        .get_uint(0)
+}
+
+/// Bring an image stored with the given EXIF orientation upright.
+fn orient(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    // 3x2 image whose pixels encode their own coordinates
+    fn coords() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(3, 2, |x, y| {
+            Rgba([x as u8, y as u8, 0, 255])
+        }))
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Ok(Crop {
+                x: 1,
+                y: 2,
+                width: 3,
+                height: 4
+            }),
+            "1,2,3,4".parse::<Crop>().map_err(|_| ())
+        );
+        assert!("1,2,3".parse::<Crop>().is_err());
+        assert!("1,2,0,4".parse::<Crop>().is_err());
+        assert!("45".parse::<Rotation>().is_err());
+        assert_eq!(Flip::Vertical, "v".parse::<Flip>().unwrap());
+    }
+
+    #[test]
+    fn test_crop_clamps_to_image() -> Result<(), UnicodeArtError> {
+        let transform = Transform {
+            crop: Some(Crop {
+                x: 1,
+                y: 1,
+                width: 10,
+                height: 10,
+            }),
+            ..Default::default()
+        };
+        let img = transform.apply(coords())?;
+        assert_eq!((2, 1), img.dimensions());
+        assert_eq!(Rgba([1, 1, 0, 255]), img.get_pixel(0, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_crop_outside() {
+        let transform = Transform {
+            crop: Some(Crop {
+                x: 3,
+                y: 0,
+                width: 1,
+                height: 1,
+            }),
+            ..Default::default()
+        };
+        assert!(transform.apply(coords()).is_err());
+    }
+
+    #[test]
+    fn test_exif_orientation() -> Result<(), UnicodeArtError> {
+        // transpose: rows become columns
+        let transform = Transform {
+            orientation: Some(5),
+            ..Default::default()
+        };
+        let img = transform.apply(coords())?;
+        assert_eq!((2, 3), img.dimensions());
+        for (x, y, pixel) in img.pixels() {
+            assert_eq!(Rgba([y as u8, x as u8, 0, 255]), pixel);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotate_then_flip() -> Result<(), UnicodeArtError> {
+        let transform = Transform {
+            rotate: Some(Rotation::Rotate90),
+            flip: Some(Flip::Horizontal),
+            ..Default::default()
+        };
+        let img = transform.apply(coords())?;
+        // rotating clockwise and mirroring is a transpose
+        assert_eq!(Rgba([2, 1, 0, 255]), img.get_pixel(1, 2));
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+use fontdue::{Font, FontSettings, LineMetrics};
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use super::error::UnicodeArtError;
+use super::figlet::Justify;
+use super::gradient::Gradient;
+
+/// Height of the text in pixels, large enough for smooth curves once a
+/// renderer scales it down to the terminal
+pub const DEFAULT_PIXEL_SIZE: f32 = 64.0;
+const INK_COLOR: [u8; 3] = [0, 0, 0];
+
+/// Text drawn with a TrueType or OpenType font, as a bitmap for the image
+/// renderers.
+pub struct TtfText {
+    font: Font,
+    text: String,
+    px: f32,
+    justify: Justify,
+    gradient: Option<Gradient>,
+}
+
+/// A glyph bitmap at its offset from the start of the line.
+struct PlacedGlyph {
+    x: f32,
+    metrics: fontdue::Metrics,
+    coverage: Vec<u8>,
+}
+
+impl TtfText {
+    /// `data` is the content of a `.ttf` or `.otf` file.
+    pub fn new(data: &[u8], text: &str) -> Result<Self, UnicodeArtError> {
+        let font = Font::from_bytes(data, FontSettings::default()).map_err(|err| {
+            UnicodeArtError::InvalidArgumentError(format!("invalid font: {}", err))
+        })?;
+        Ok(Self {
+            font,
+            text: text.to_string(),
+            px: DEFAULT_PIXEL_SIZE,
+            justify: Justify::Left,
+            gradient: None,
+        })
+    }
+
+    pub fn with_pixel_size(mut self, px: f32) -> Self {
+        self.px = px;
+        self
+    }
+
+    /// Alignment of lines shorter than the longest one.
+    pub fn with_justify(mut self, justify: Justify) -> Self {
+        self.justify = justify;
+        self
+    }
+
+    /// Color the text with a gradient from the left to the right.
+    pub fn with_gradient(mut self, gradient: Gradient) -> Self {
+        self.gradient = Some(gradient);
+        self
+    }
+
+    fn line_metrics(&self) -> LineMetrics {
+        self.font
+            .horizontal_line_metrics(self.px)
+            .unwrap_or(LineMetrics {
+                ascent: self.px,
+                descent: 0.0,
+                line_gap: 0.0,
+                new_line_size: self.px,
+            })
+    }
+
+    /// Glyphs of a line with kerning, and the width of the line.
+    fn layout_line(&self, line: &str) -> (Vec<PlacedGlyph>, f32) {
+        let mut glyphs = Vec::new();
+        let mut pen = 0.0;
+        let mut width: f32 = 0.0;
+        let mut previous = None;
+        for ch in line.chars() {
+            if let Some(kern) =
+                previous.and_then(|left| self.font.horizontal_kern(left, ch, self.px))
+            {
+                pen += kern;
+            }
+            let (metrics, coverage) = self.font.rasterize(ch, self.px);
+            let right = pen + metrics.xmin as f32 + metrics.width as f32;
+            width = width.max(right).max(pen + metrics.advance_width);
+            glyphs.push(PlacedGlyph {
+                x: pen,
+                metrics,
+                coverage,
+            });
+            pen += metrics.advance_width;
+            previous = Some(ch);
+        }
+        (glyphs, width)
+    }
+
+    /// Draw the text at the pixel size. The ink is opaque where the glyphs
+    /// cover a pixel and the background is transparent, so renderers leave
+    /// it to the terminal.
+    pub fn rasterize(&self) -> DynamicImage {
+        let line_metrics = self.line_metrics();
+        let lines: Vec<_> = self
+            .text
+            .lines()
+            .map(|line| self.layout_line(line))
+            .collect();
+        let width = lines.iter().map(|(_, width)| *width).fold(0.0, f32::max);
+        let width = (width.ceil() as u32).max(1);
+        let height = ((lines.len() as f32 * line_metrics.new_line_size).ceil() as u32).max(1);
+        let last = (width.max(2) - 1) as f64;
+
+        let mut image = RgbaImage::new(width, height);
+        for (row, (glyphs, line_width)) in lines.iter().enumerate() {
+            let offset = match self.justify {
+                Justify::Left => 0.0,
+                Justify::Center => ((width as f32 - line_width) / 2.0).floor(),
+                Justify::Right => (width as f32 - line_width).floor(),
+            };
+            let baseline = row as f32 * line_metrics.new_line_size + line_metrics.ascent;
+            for glyph in glyphs {
+                let metrics = &glyph.metrics;
+                let left = (offset + glyph.x).round() as i64 + metrics.xmin as i64;
+                let top = baseline.round() as i64 - metrics.ymin as i64 - metrics.height as i64;
+                for (i, &coverage) in glyph.coverage.iter().enumerate() {
+                    let x = left + (i % metrics.width.max(1)) as i64;
+                    let y = top + (i / metrics.width.max(1)) as i64;
+                    if coverage == 0 || x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+                        continue;
+                    }
+                    let [r, g, b] = match &self.gradient {
+                        Some(gradient) => {
+                            let color = gradient.at(x as f64 / last);
+                            [color[0], color[1], color[2]]
+                        }
+                        None => INK_COLOR,
+                    };
+                    // neighbouring glyphs may overlap, the most ink wins
+                    let pixel = image.get_pixel_mut(x as u32, y as u32);
+                    *pixel = Rgba([r, g, b, pixel[3].max(coverage)]);
+                }
+            }
+        }
+        DynamicImage::ImageRgba8(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unicode_art::braille::BrailleAsciiArtOption;
+    use crate::unicode_art::UnicodeArtOption;
+    use image::GenericImageView;
+
+    // a handful of rectangular glyphs: space, -, H and I
+    const TEST_FONT: &[u8] = include_bytes!("../../tests/support/test_font.ttf");
+
+    #[test]
+    fn test_rasterize() -> Result<(), UnicodeArtError> {
+        let image = TtfText::new(TEST_FONT, "HI")?
+            .with_pixel_size(10.0)
+            .rasterize();
+        // advances of 7 and 4 pixels, a line of ascent 8 and descent 2
+        assert_eq!((11, 10), image.dimensions());
+        assert_eq!(Rgba([0, 0, 0, 255]), image.get_pixel(1, 2));
+        assert_eq!(0, image.get_pixel(3, 2)[3]);
+        assert_eq!(0, image.get_pixel(1, 9)[3]);
+
+        let image = TtfText::new(TEST_FONT, "I\nHI")?
+            .with_pixel_size(10.0)
+            .with_justify(Justify::Right)
+            .rasterize();
+        assert_eq!((11, 20), image.dimensions());
+        assert_eq!(0, image.get_pixel(1, 2)[3]);
+        assert_eq!(255, image.get_pixel(8, 2)[3]);
+
+        assert!(TtfText::new(b"not a font", "HI").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_ttf_braille() -> Result<(), UnicodeArtError> {
+        let image = TtfText::new(TEST_FONT, "HI-H")?
+            .with_pixel_size(24.0)
+            .rasterize();
+        let option = BrailleAsciiArtOption::new(24, 128, false, false);
+        let mut buffer = Vec::new();
+        option.new_unicode_art(&image)?.write_all(&mut buffer)?;
+        let actual = std::str::from_utf8(&buffer).unwrap();
+        assert_eq!(
+            "⢠⣶⡆⠀⢰⣶⠀⠀⣴⣶⠀⠀⠀⠀⠀⠀⠀⣴⡆⠀⠀⣶⠀⠀\n\
+             ⢸⣿⣇⣀⣸⣿⠀⠀⣿⣿⠀⠀⢀⣀⣀⠀⠀⣿⣇⣀⣀⣿⠀⠀\n\
+             ⢸⣿⡟⠛⢻⣿⠀⠀⣿⣿⠀⠀⠛⠛⠛⠀⠀⣿⡟⠛⠛⣿⠀⠀\n\
+             ⠸⣿⡇⠀⢸⣿⠀⠀⢿⣿⠀⠀⠀⠀⠀⠀⠀⢿⡇⠀⠀⣿⠀⠀\n\
+             ⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀\n",
+            actual
+        );
+        Ok(())
+    }
+}